@@ -0,0 +1,585 @@
+use crate::chip8::PROGRAM_START;
+use std::collections::HashMap;
+use std::fmt;
+
+///
+/// What went wrong while assembling a source.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    /// The source ends in the middle of a statement.
+    UnexpectedEnd,
+    /// The token does not start any statement.
+    UnknownInstruction(String),
+    /// The statement needs something else at this place.
+    Expected {
+        expected: &'static str,
+        found: String,
+    },
+    /// The label, alias or constant is never defined.
+    UnknownName(String),
+    /// The label, alias or constant is already defined.
+    DuplicateName(String),
+    /// The value does not fit in the operand.
+    OutOfRange { value: i64, max: i64 },
+    /// `else`, `end` or `again` without the statement opening their block.
+    UnmatchedBlock(String),
+    /// `if ... begin` or `loop` never closed.
+    UnclosedBlock(String),
+}
+
+///
+/// Error returned when a source cannot be assembled, located at the 1-based line and column
+/// of the faulty token.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: AsmErrorKind,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            AsmErrorKind::UnexpectedEnd => write!(f, "unexpected end of source"),
+            AsmErrorKind::UnknownInstruction(token) => write!(f, "unknown instruction '{}'", token),
+            AsmErrorKind::Expected { expected, found } => {
+                write!(f, "expected {}, found '{}'", expected, found)
+            }
+            AsmErrorKind::UnknownName(name) => write!(f, "undefined name '{}'", name),
+            AsmErrorKind::DuplicateName(name) => write!(f, "'{}' is already defined", name),
+            AsmErrorKind::OutOfRange { value, max } => {
+                write!(f, "value {} out of range, expected at most {}", value, max)
+            }
+            AsmErrorKind::UnmatchedBlock(token) => write!(f, "'{}' closes no block", token),
+            AsmErrorKind::UnclosedBlock(token) => write!(f, "'{}' is never closed", token),
+        }
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+    column: usize,
+}
+
+impl Token<'_> {
+    fn error(&self, kind: AsmErrorKind) -> AsmError {
+        AsmError {
+            line: self.line,
+            column: self.column,
+            kind,
+        }
+    }
+
+    fn expected(&self, expected: &'static str) -> AsmError {
+        self.error(AsmErrorKind::Expected {
+            expected,
+            found: self.text.to_string(),
+        })
+    }
+}
+
+// Splits the source on whitespace, dropping `#` comments.
+fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let code = line.split('#').next().unwrap_or_default();
+        let mut rest = code;
+        while let Some(start) = rest.find(|c: char| !c.is_whitespace()) {
+            let length = rest[start..]
+                .find(char::is_whitespace)
+                .unwrap_or(rest.len() - start);
+            tokens.push(Token {
+                text: &rest[start..start + length],
+                line: index + 1,
+                column: code[..code.len() - rest.len() + start].chars().count() + 1,
+            });
+            rest = &rest[start + length..];
+        }
+    }
+    tokens
+}
+
+fn number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn register(text: &str) -> Option<u16> {
+    let digit = text.strip_prefix('v').or_else(|| text.strip_prefix('V'))?;
+    if digit.len() == 1 {
+        u16::from_str_radix(digit, 16).ok()
+    } else {
+        None
+    }
+}
+
+// Address fields patched once every label is known.
+#[derive(Debug, Clone, Copy)]
+enum Width {
+    // The 12 lowest bits of an opcode.
+    Nnn,
+    // The 16 bits following F000.
+    Long,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Block<'a> {
+    // `loop`, jumped back to by `again`.
+    Loop(Token<'a>, u16),
+    // `if ... begin` and `else`, with the offset of the jump skipping their body.
+    If(Token<'a>, usize),
+    Else(Token<'a>, usize),
+}
+
+struct Assembler<'a> {
+    tokens: Vec<Token<'a>>,
+    position: usize,
+    output: Vec<u8>,
+    labels: HashMap<&'a str, u16>,
+    aliases: HashMap<&'a str, u16>,
+    constants: HashMap<&'a str, i64>,
+    fixups: Vec<(usize, Width, Token<'a>)>,
+    blocks: Vec<Block<'a>>,
+}
+
+///
+/// Assembles a source written in a subset of the Octo syntax into a rom loaded at
+/// [`PROGRAM_START`].
+///
+/// Supported statements are every instruction as written by Octo, `: label` definitions,
+/// `:alias name vX`, `:const name value`, `:byte value`, `:call address`, bare numbers as data
+/// bytes and bare labels as subroutine calls. Control flow can use `if ... then`,
+/// `if ... begin ... else ... end` and `loop ... again`. Comments start with `#`.
+///
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let mut assembler = Assembler {
+        tokens: tokenize(source),
+        position: 0,
+        output: Vec::new(),
+        labels: HashMap::new(),
+        aliases: HashMap::new(),
+        constants: HashMap::new(),
+        fixups: Vec::new(),
+        blocks: Vec::new(),
+    };
+    while assembler.position < assembler.tokens.len() {
+        assembler.statement()?;
+    }
+    assembler.finish()
+}
+
+impl<'a> Assembler<'a> {
+    fn next(&mut self) -> Result<Token<'a>, AsmError> {
+        let token = self.tokens.get(self.position).copied().ok_or_else(|| {
+            let last = self.tokens.last().map_or((1, 1), |token| {
+                (token.line, token.column + token.text.chars().count())
+            });
+            AsmError {
+                line: last.0,
+                column: last.1,
+                kind: AsmErrorKind::UnexpectedEnd,
+            }
+        })?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).map(|token| token.text)
+    }
+
+    fn expect(&mut self, text: &'static str) -> Result<(), AsmError> {
+        let token = self.next()?;
+        if token.text == text {
+            Ok(())
+        } else {
+            Err(token.expected(text))
+        }
+    }
+
+    fn address(&self) -> u16 {
+        (PROGRAM_START + self.output.len()) as u16
+    }
+
+    fn emit(&mut self, opcode: u16) {
+        self.output.extend_from_slice(&opcode.to_be_bytes());
+    }
+
+    fn register(&mut self) -> Result<u16, AsmError> {
+        let token = self.next()?;
+        self.aliases
+            .get(token.text)
+            .copied()
+            .or_else(|| register(token.text))
+            .ok_or_else(|| token.expected("a register"))
+    }
+
+    fn value(&mut self, max: i64) -> Result<u16, AsmError> {
+        let token = self.next()?;
+        let value = number(token.text)
+            .or_else(|| self.constants.get(token.text).copied())
+            .ok_or_else(|| token.expected("a number"))?;
+        // Negative bytes are written in two's complement.
+        let value = if max == 0xFF && (-0x80..0).contains(&value) {
+            value + 0x100
+        } else {
+            value
+        };
+        if !(0..=max).contains(&value) {
+            return Err(token.error(AsmErrorKind::OutOfRange { value, max }));
+        }
+        Ok(value as u16)
+    }
+
+    fn name(&mut self) -> Result<Token<'a>, AsmError> {
+        let token = self.next()?;
+        let taken = self.labels.contains_key(token.text)
+            || self.aliases.contains_key(token.text)
+            || self.constants.contains_key(token.text);
+        if number(token.text).is_some() || register(token.text).is_some() {
+            Err(token.expected("a name"))
+        } else if taken {
+            Err(token.error(AsmErrorKind::DuplicateName(token.text.to_string())))
+        } else {
+            Ok(token)
+        }
+    }
+
+    // Emits an instruction whose address is a number, a constant or a label defined anywhere.
+    fn emit_address(&mut self, prefix: u16, width: Width) -> Result<(), AsmError> {
+        let token = self.next()?;
+        if let Width::Long = width {
+            self.emit(0xF000);
+        }
+        let known = number(token.text).or_else(|| self.constants.get(token.text).copied());
+        match known {
+            Some(value) => {
+                self.emit(prefix);
+                self.patch(self.output.len() - 2, width, value, token)?;
+            }
+            None if token
+                .text
+                .starts_with(|c: char| c.is_ascii_digit() || c == '-') =>
+            {
+                return Err(token.expected("an address"));
+            }
+            None => {
+                self.fixups.push((self.output.len(), width, token));
+                self.emit(prefix);
+            }
+        }
+        Ok(())
+    }
+
+    fn patch(
+        &mut self,
+        offset: usize,
+        width: Width,
+        value: i64,
+        token: Token,
+    ) -> Result<(), AsmError> {
+        let max = match width {
+            Width::Nnn => 0xFFF,
+            Width::Long => 0xFFFF,
+        };
+        if !(0..=max).contains(&value) {
+            return Err(token.error(AsmErrorKind::OutOfRange { value, max }));
+        }
+        let field = &mut self.output[offset..offset + 2];
+        let opcode = u16::from_be_bytes([field[0], field[1]]) & !(max as u16) | value as u16;
+        field.copy_from_slice(&opcode.to_be_bytes());
+        Ok(())
+    }
+
+    // Patches the jump at `offset` to the current address.
+    fn land(&mut self, offset: usize, token: Token) -> Result<(), AsmError> {
+        self.patch(offset, Width::Nnn, self.address() as i64, token)
+    }
+
+    // Returns the instruction skipping the next one unless the condition holds,
+    // and the one skipping it when the condition holds.
+    fn condition(&mut self) -> Result<(u16, u16), AsmError> {
+        let x = self.register()? << 8;
+        let operator = self.next()?;
+        let (opcode, negated) = match operator.text {
+            "key" => (0xE0A1 | x, 0xE09E | x),
+            "-key" => (0xE09E | x, 0xE0A1 | x),
+            "==" | "!=" => {
+                let is_register = self.peek().is_some_and(|text| {
+                    self.aliases.contains_key(text) || register(text).is_some()
+                });
+                let (equal, different) = if is_register {
+                    let y = self.register()? << 4;
+                    (0x9000 | x | y, 0x5000 | x | y)
+                } else {
+                    let nn = self.value(0xFF)?;
+                    (0x4000 | x | nn, 0x3000 | x | nn)
+                };
+                if operator.text == "==" {
+                    (equal, different)
+                } else {
+                    (different, equal)
+                }
+            }
+            _ => return Err(operator.expected("'==', '!=', 'key' or '-key'")),
+        };
+        Ok((opcode, negated))
+    }
+
+    fn statement(&mut self) -> Result<(), AsmError> {
+        let token = self.next()?;
+        match token.text {
+            ":" => {
+                let name = self.name()?;
+                let address = self.address();
+                self.labels.insert(name.text, address);
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.register()?;
+                self.aliases.insert(name.text, register);
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.value(0xFFFF)?;
+                self.constants.insert(name.text, value as i64);
+            }
+            ":byte" => {
+                let byte = self.value(0xFF)?;
+                self.output.push(byte as u8);
+            }
+            ":call" => self.emit_address(0x2000, Width::Nnn)?,
+            "clear" => self.emit(0x00E0),
+            "return" | ";" => self.emit(0x00EE),
+            "scroll-down" => {
+                let n = self.value(0xF)?;
+                // 00C0 is not an instruction.
+                if n == 0 {
+                    self.position -= 1;
+                    return Err(self.next()?.expected("1 to 15 lines"));
+                }
+                self.emit(0x00C0 | n);
+            }
+            "scroll-up" => {
+                let n = self.value(0xF)?;
+                self.emit(0x00D0 | n);
+            }
+            "scroll-right" => self.emit(0x00FB),
+            "scroll-left" => self.emit(0x00FC),
+            "exit" => self.emit(0x00FD),
+            "lores" => self.emit(0x00FE),
+            "hires" => self.emit(0x00FF),
+            "jump" => self.emit_address(0x1000, Width::Nnn)?,
+            "jump0" => self.emit_address(0xB000, Width::Nnn)?,
+            "audio" => self.emit(0xF002),
+            "plane" => {
+                let n = self.value(0xF)?;
+                self.emit(0xF001 | n << 8);
+            }
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let n = self.value(0xF)?;
+                self.emit(0xD000 | x << 8 | y << 4 | n);
+            }
+            "bcd" => {
+                let x = self.register()?;
+                self.emit(0xF033 | x << 8);
+            }
+            "save" | "load" => {
+                let x = self.register()?;
+                if self.peek() == Some("-") {
+                    self.next()?;
+                    let y = self.register()?;
+                    let kind = if token.text == "save" { 0x2 } else { 0x3 };
+                    self.emit(0x5000 | x << 8 | y << 4 | kind);
+                } else {
+                    let kind = if token.text == "save" { 0x55 } else { 0x65 };
+                    self.emit(0xF000 | x << 8 | kind);
+                }
+            }
+            "saveflags" => {
+                let x = self.register()?;
+                self.emit(0xF075 | x << 8);
+            }
+            "loadflags" => {
+                let x = self.register()?;
+                self.emit(0xF085 | x << 8);
+            }
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.register()? << 8;
+                self.emit(match token.text {
+                    "delay" => 0xF015 | x,
+                    "buzzer" => 0xF018 | x,
+                    _ => 0xF03A | x,
+                });
+            }
+            "i" => self.index()?,
+            "if" => {
+                let (opcode, negated) = self.condition()?;
+                let keyword = self.next()?;
+                match keyword.text {
+                    "then" => self.emit(opcode),
+                    // The body is jumped over unless the condition holds.
+                    "begin" => {
+                        self.emit(negated);
+                        self.blocks.push(Block::If(token, self.output.len()));
+                        self.emit(0x1000);
+                    }
+                    _ => return Err(keyword.expected("'then' or 'begin'")),
+                }
+            }
+            "else" => match self.blocks.pop() {
+                Some(Block::If(opening, offset)) => {
+                    self.emit(0x1000);
+                    self.land(offset, opening)?;
+                    self.blocks.push(Block::Else(token, self.output.len() - 2));
+                }
+                _ => return Err(token.error(AsmErrorKind::UnmatchedBlock("else".into()))),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If(opening, offset) | Block::Else(opening, offset)) => {
+                    self.land(offset, opening)?
+                }
+                _ => return Err(token.error(AsmErrorKind::UnmatchedBlock("end".into()))),
+            },
+            "loop" => self.blocks.push(Block::Loop(token, self.address())),
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop(opening, address)) => {
+                    self.emit(0x1000);
+                    self.patch(self.output.len() - 2, Width::Nnn, address as i64, opening)?;
+                }
+                _ => return Err(token.error(AsmErrorKind::UnmatchedBlock("again".into()))),
+            },
+            text if self.aliases.contains_key(text) || register(text).is_some() => {
+                self.position -= 1;
+                self.assignment()?;
+            }
+            text => match number(text) {
+                Some(_) => {
+                    self.position -= 1;
+                    let byte = self.value(0xFF)?;
+                    self.output.push(byte as u8);
+                }
+                None if text.starts_with(':') => {
+                    return Err(token.error(AsmErrorKind::UnknownInstruction(text.into())));
+                }
+                // A bare label calls the subroutine it names.
+                None => {
+                    self.position -= 1;
+                    self.emit_address(0x2000, Width::Nnn)?;
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn index(&mut self) -> Result<(), AsmError> {
+        let operator = self.next()?;
+        match operator.text {
+            ":=" => match self.peek() {
+                Some("hex") | Some("bighex") => {
+                    let kind = if self.next()?.text == "hex" {
+                        0x29
+                    } else {
+                        0x30
+                    };
+                    let x = self.register()?;
+                    self.emit(0xF000 | x << 8 | kind);
+                }
+                Some("long") => {
+                    self.next()?;
+                    self.emit_address(0x0000, Width::Long)?;
+                }
+                _ => self.emit_address(0xA000, Width::Nnn)?,
+            },
+            "+=" => {
+                let x = self.register()?;
+                self.emit(0xF01E | x << 8);
+            }
+            _ => return Err(operator.expected("':=' or '+='")),
+        }
+        Ok(())
+    }
+
+    fn assignment(&mut self) -> Result<(), AsmError> {
+        let x = self.register()? << 8;
+        let operator = self.next()?;
+        let is_register = self
+            .peek()
+            .is_some_and(|text| self.aliases.contains_key(text) || register(text).is_some());
+        let alu = match operator.text {
+            ":=" => 0x0,
+            "|=" => 0x1,
+            "&=" => 0x2,
+            "^=" => 0x3,
+            "+=" => 0x4,
+            "-=" => 0x5,
+            ">>=" => 0x6,
+            "=-" => 0x7,
+            "<<=" => 0xE,
+            _ => return Err(operator.expected("an assignment operator")),
+        };
+        if is_register {
+            let y = self.register()? << 4;
+            self.emit(0x8000 | x | y | alu);
+            return Ok(());
+        }
+        let opcode = match (operator.text, self.peek()) {
+            (":=", Some("random")) => {
+                self.next()?;
+                0xC000 | x | self.value(0xFF)?
+            }
+            (":=", Some("delay")) => {
+                self.next()?;
+                0xF007 | x
+            }
+            (":=", Some("key")) => {
+                self.next()?;
+                0xF00A | x
+            }
+            (":=", _) => 0x6000 | x | self.value(0xFF)?,
+            ("+=", _) => 0x7000 | x | self.value(0xFF)?,
+            ("-=", _) => 0x7000 | x | (0x100 - self.value(0xFF)?) & 0xFF,
+            _ => return Err(self.next()?.expected("a register")),
+        };
+        self.emit(opcode);
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, AsmError> {
+        if let Some(block) = self.blocks.pop() {
+            let (Block::Loop(token, _) | Block::If(token, _) | Block::Else(token, _)) = block;
+            return Err(token.error(AsmErrorKind::UnclosedBlock(token.text.into())));
+        }
+        for (offset, width, token) in std::mem::take(&mut self.fixups) {
+            let address = self
+                .labels
+                .get(token.text)
+                .copied()
+                .ok_or_else(|| token.error(AsmErrorKind::UnknownName(token.text.into())))?;
+            self.patch(offset, width, address as i64, token)?;
+        }
+        Ok(self.output)
+    }
+}
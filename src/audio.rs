@@ -0,0 +1,474 @@
+use crate::chip8::Chip8;
+use crate::variant::Variant;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+///
+/// Frequency of the buzzer tone in Hz, when not configured.
+///
+pub const DEFAULT_FREQUENCY: f32 = 440.0;
+
+///
+/// Volume of the buzzer tone, from 0 to 1, when not configured.
+///
+pub const DEFAULT_VOLUME: f32 = 0.25;
+
+///
+/// Sample rate of the WAV recordings, in Hz.
+///
+pub const WAV_SAMPLE_RATE: u32 = 44_100;
+
+// Frames per second, the rate the sound timer ticks at.
+const FRAME_RATE: u32 = 60;
+
+// Playback rate of the XO-CHIP audio pattern in bits per second, for the default pitch of 64.
+const PATTERN_RATE: f32 = 4000.0;
+
+///
+/// Shape of the buzzer tone.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Waveform {
+    #[default]
+    Square,
+    Triangle,
+    Sawtooth,
+    Sine,
+}
+
+impl Waveform {
+    ///
+    /// Returns the value of the wave, from -1 to 1, at the given position within its period,
+    /// from 0 to 1.
+    ///
+    pub fn sample(self, phase: f32) -> f32 {
+        match self {
+            Waveform::Square if phase < 0.5 => 1.0,
+            Waveform::Square => -1.0,
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+            Waveform::Sine => (2.0 * std::f32::consts::PI * phase).sin(),
+        }
+    }
+}
+
+///
+/// Error returned when parsing an unknown waveform name.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownWaveform(pub String);
+
+impl fmt::Display for UnknownWaveform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown waveform '{}', expected one of: square, triangle, sawtooth, sine",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownWaveform {}
+
+impl FromStr for Waveform {
+    type Err = UnknownWaveform;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "square" => Ok(Waveform::Square),
+            "triangle" => Ok(Waveform::Triangle),
+            "sawtooth" | "saw" => Ok(Waveform::Sawtooth),
+            "sine" => Ok(Waveform::Sine),
+            _ => Err(UnknownWaveform(name.to_string())),
+        }
+    }
+}
+
+///
+/// Configuration of the buzzer tone.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioSettings {
+    /// Frequency of the tone in Hz.
+    pub frequency: f32,
+    /// Volume of the tone, from 0 for silence to 1.
+    pub volume: f32,
+    pub waveform: Waveform,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            frequency: DEFAULT_FREQUENCY,
+            volume: DEFAULT_VOLUME,
+            waveform: Waveform::default(),
+        }
+    }
+}
+
+// What the synthesizer is playing, as of the last frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tone {
+    Silent,
+    Wave,
+    // XO-CHIP audio pattern, with its playback rate in bits per second.
+    Pattern { bits: [u8; 16], rate: f32 },
+}
+
+///
+/// Generates the samples of the buzzer from the state of the machine.
+///
+/// The configured waveform plays while the buzzer sounds, except on XO-CHIP machines that
+/// loaded an audio pattern: the 128 bits of the pattern are played instead, at the rate set
+/// by the pitch register.
+///
+#[derive(Debug, Clone)]
+pub struct Synthesizer {
+    pub settings: AudioSettings,
+    pub sample_rate: u32,
+    tone: Tone,
+    // Position within the period of the wave, or within the bits of the pattern.
+    phase: f32,
+    // Frames the buzzer of the machine had been sounding for at the last update.
+    sound_frames: u32,
+}
+
+impl Synthesizer {
+    pub fn new(settings: AudioSettings, sample_rate: u32) -> Self {
+        Self {
+            settings,
+            sample_rate,
+            tone: Tone::Silent,
+            phase: 0.0,
+            sound_frames: 0,
+        }
+    }
+
+    ///
+    /// Follows the buzzer of the machine, to be called after each frame.
+    ///
+    /// A tone runs on from one frame to the next. When the machine did not just run the frame
+    /// following the last update, after loading a state, rewinding or pausing, the tone resumes
+    /// where the machine is within it rather than from the start of its waveform.
+    ///
+    pub fn update(&mut self, vm: &Chip8) {
+        let resumed = !self.is_playing() || vm.sound_frames != self.sound_frames.wrapping_add(1);
+        self.sound_frames = vm.sound_frames;
+        self.tone = if !vm.buzzer {
+            Tone::Silent
+        } else if vm.variant == Variant::XoChip && vm.audio_pattern != [0; 16] {
+            Tone::Pattern {
+                bits: vm.audio_pattern,
+                rate: PATTERN_RATE * 2f32.powf((vm.pitch as f32 - 64.0) / 48.0),
+            }
+        } else {
+            Tone::Wave
+        };
+        if resumed {
+            // Time the tone has been playing for, until the frame starting now.
+            let elapsed = vm.sound_frames.saturating_sub(1) as f64 / FRAME_RATE as f64;
+            self.phase = match self.tone {
+                Tone::Silent => 0.0,
+                Tone::Wave => (elapsed * self.settings.frequency as f64).fract() as f32,
+                Tone::Pattern { rate, .. } => (elapsed * rate as f64 % 128.0) as f32,
+            };
+        }
+    }
+
+    ///
+    /// Silences the synthesizer until the next update.
+    ///
+    pub fn silence(&mut self) {
+        self.tone = Tone::Silent;
+    }
+
+    ///
+    /// Whether the synthesizer is playing anything.
+    ///
+    pub fn is_playing(&self) -> bool {
+        self.tone != Tone::Silent
+    }
+
+    ///
+    /// Fills the buffer with the next mono samples, from -1 to 1.
+    ///
+    pub fn fill(&mut self, samples: &mut [f32]) {
+        let volume = self.settings.volume.clamp(0.0, 1.0);
+        for sample in samples {
+            *sample = match self.tone {
+                Tone::Silent => {
+                    // Tones start at the beginning of their period.
+                    self.phase = 0.0;
+                    0.0
+                }
+                Tone::Wave => {
+                    let value = self.settings.waveform.sample(self.phase);
+                    self.phase =
+                        (self.phase + self.settings.frequency / self.sample_rate as f32).fract();
+                    value * volume
+                }
+                Tone::Pattern { bits, rate } => {
+                    let bit = self.phase as usize;
+                    let lit = bits[bit / 8] & (0x80 >> (bit % 8)) != 0;
+                    self.phase = (self.phase + rate / self.sample_rate as f32) % 128.0;
+                    if lit {
+                        volume
+                    } else {
+                        -volume
+                    }
+                }
+            };
+        }
+    }
+}
+
+///
+/// Destination of the sound of the machine, fed once per frame.
+///
+pub trait AudioSink {
+    ///
+    /// Plays the sound of the frame the machine just ran.
+    ///
+    fn frame(&mut self, vm: &Chip8);
+
+    ///
+    /// Silences the output while no frame runs, such as when the machine is paused.
+    ///
+    fn pause(&mut self) {}
+}
+
+///
+/// Sink discarding the sound.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Mute;
+
+impl AudioSink for Mute {
+    fn frame(&mut self, _: &Chip8) {}
+}
+
+///
+/// Sink ringing the terminal bell each time the buzzer starts, for systems without a sound
+/// device. Neither the duration nor the settings of the tone are honoured.
+///
+#[derive(Debug)]
+pub struct Bell<W: Write> {
+    writer: W,
+    ringing: bool,
+}
+
+impl<W: Write> Bell<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            ringing: false,
+        }
+    }
+}
+
+impl<W: Write> AudioSink for Bell<W> {
+    fn frame(&mut self, vm: &Chip8) {
+        if vm.buzzer && !self.ringing {
+            // A bell that cannot ring is not worth stopping the emulation for.
+            let _ = self
+                .writer
+                .write_all(b"\x07")
+                .and_then(|()| self.writer.flush());
+        }
+        self.ringing = vm.buzzer;
+    }
+
+    fn pause(&mut self) {
+        self.ringing = false;
+    }
+}
+
+///
+/// Sink recording the sound as 16-bit mono samples, exported as a WAV file.
+///
+/// ```
+/// use rusty_chip8::audio::{AudioSettings, AudioSink, WavRecorder, WAV_SAMPLE_RATE};
+/// use rusty_chip8::Chip8;
+///
+/// let mut vm = Chip8::default();
+/// let mut recorder = WavRecorder::new(AudioSettings::default());
+/// vm.sound_timer = 1;
+/// vm.tick_timers();
+/// recorder.frame(&vm);
+/// vm.tick_timers();
+/// recorder.frame(&vm);
+/// let samples_per_frame = WAV_SAMPLE_RATE as usize / 60;
+/// assert_eq!(recorder.samples().len(), 2 * samples_per_frame);
+/// assert!(recorder.samples()[..samples_per_frame].iter().all(|&sample| sample != 0));
+/// assert!(recorder.samples()[samples_per_frame..].iter().all(|&sample| sample == 0));
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct WavRecorder {
+    synthesizer: Synthesizer,
+    samples: Vec<i16>,
+    // Samples per frame not being a whole number, the fraction carried over, in 1/60 samples.
+    carry: u32,
+}
+
+impl WavRecorder {
+    pub fn new(settings: AudioSettings) -> Self {
+        Self {
+            synthesizer: Synthesizer::new(settings, WAV_SAMPLE_RATE),
+            samples: Vec::new(),
+            carry: 0,
+        }
+    }
+
+    ///
+    /// Returns the samples recorded so far.
+    ///
+    pub fn samples(&self) -> &[i16] {
+        &self.samples
+    }
+
+    ///
+    /// Encodes the recording as a 16-bit PCM mono WAV file.
+    ///
+    pub fn to_wav(&self) -> Vec<u8> {
+        let data_size = (self.samples.len() * 2) as u32;
+        let sample_rate = self.synthesizer.sample_rate;
+        let mut wav = Vec::with_capacity(44 + data_size as usize);
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_size).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        // PCM format, one channel.
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&sample_rate.to_le_bytes());
+        // Bytes per second, bytes per sample, bits per sample.
+        wav.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_size.to_le_bytes());
+        for sample in &self.samples {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+        wav
+    }
+}
+
+impl AudioSink for WavRecorder {
+    fn frame(&mut self, vm: &Chip8) {
+        self.synthesizer.update(vm);
+        let samples = (self.carry + self.synthesizer.sample_rate) / FRAME_RATE;
+        self.carry = (self.carry + self.synthesizer.sample_rate) % FRAME_RATE;
+        let mut buffer = vec![0.0; samples as usize];
+        self.synthesizer.fill(&mut buffer);
+        self.samples.extend(
+            buffer
+                .iter()
+                .map(|&sample| (sample * i16::MAX as f32) as i16),
+        );
+    }
+}
+
+#[cfg(feature = "audio")]
+pub use device::{Device, DeviceError};
+
+#[cfg(feature = "audio")]
+mod device {
+    use super::{AudioSettings, AudioSink, Synthesizer};
+    use crate::chip8::Chip8;
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use cpal::{FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+
+    ///
+    /// Error returned when the default sound device cannot be opened.
+    ///
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct DeviceError(pub String);
+
+    impl fmt::Display for DeviceError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "cannot open the sound device: {}", self.0)
+        }
+    }
+
+    impl std::error::Error for DeviceError {}
+
+    ///
+    /// Sink playing the sound on the default output device of the system.
+    ///
+    pub struct Device {
+        synthesizer: Arc<Mutex<Synthesizer>>,
+        // Playing as long as it is not dropped.
+        _stream: Stream,
+    }
+
+    impl Device {
+        pub fn open(settings: AudioSettings) -> Result<Self, DeviceError> {
+            let error = |error: &dyn fmt::Display| DeviceError(error.to_string());
+            let device = cpal::default_host()
+                .default_output_device()
+                .ok_or_else(|| DeviceError("no output device".into()))?;
+            let supported = device.default_output_config().map_err(|e| error(&e))?;
+            let config = supported.config();
+            let synthesizer =
+                Arc::new(Mutex::new(Synthesizer::new(settings, config.sample_rate.0)));
+            let stream = match supported.sample_format() {
+                SampleFormat::F32 => build::<f32>(&device, &config, &synthesizer),
+                SampleFormat::I16 => build::<i16>(&device, &config, &synthesizer),
+                SampleFormat::U16 => build::<u16>(&device, &config, &synthesizer),
+                format => return Err(DeviceError(format!("unsupported format {}", format))),
+            }
+            .map_err(|e| error(&e))?;
+            stream.play().map_err(|e| error(&e))?;
+            Ok(Self {
+                synthesizer,
+                _stream: stream,
+            })
+        }
+    }
+
+    // Plays the mono samples of the synthesizer on every channel of the device.
+    fn build<T: SizedSample + FromSample<f32>>(
+        device: &cpal::Device,
+        config: &StreamConfig,
+        synthesizer: &Arc<Mutex<Synthesizer>>,
+    ) -> Result<Stream, cpal::BuildStreamError> {
+        let synthesizer = Arc::clone(synthesizer);
+        let channels = config.channels as usize;
+        let mut mono = Vec::new();
+        device.build_output_stream(
+            config,
+            move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                mono.resize(data.len() / channels, 0.0);
+                if let Ok(mut synthesizer) = synthesizer.lock() {
+                    synthesizer.fill(&mut mono);
+                }
+                for (frame, &sample) in data.chunks_mut(channels).zip(&mono) {
+                    frame.fill(T::from_sample(sample));
+                }
+            },
+            // Errors cannot be reported from the audio thread without garbling the terminal,
+            // the emulation goes on silently.
+            |_| {},
+            None,
+        )
+    }
+
+    impl AudioSink for Device {
+        fn frame(&mut self, vm: &Chip8) {
+            if let Ok(mut synthesizer) = self.synthesizer.lock() {
+                synthesizer.update(vm);
+            }
+        }
+
+        fn pause(&mut self) {
+            if let Ok(mut synthesizer) = self.synthesizer.lock() {
+                synthesizer.silence();
+            }
+        }
+    }
+}
@@ -0,0 +1,428 @@
+use crossterm::event::KeyCode;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+///
+/// What pressing a bound key does.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    /// Holds down the given Chip8 key (0x0 to 0xF).
+    Keypad(u8),
+    /// Leaves the emulator.
+    Quit,
+    /// Saves the machine into the given slot (1 to [`SAVE_SLOTS`]).
+    SaveState(u8),
+    /// Restores the machine saved into the given slot (1 to [`SAVE_SLOTS`]).
+    LoadState(u8),
+    /// Runs the machine backward while held down.
+    Rewind,
+    /// Shows or hides the debugger panel.
+    Debugger,
+    /// Pauses the machine, or resumes it.
+    Pause,
+    /// Executes the next instruction of the paused machine.
+    Step,
+    /// Executes the next instruction of the paused machine, running called subroutines
+    /// until they return.
+    StepOver,
+    /// Runs the paused machine until the current subroutine returns.
+    StepOut,
+    /// Sets a breakpoint at the program counter, or removes it.
+    Breakpoint,
+    /// Shows the memory panel and gives it the keyboard, or hides it.
+    Memory,
+}
+
+///
+/// Number of save state slots, each with its own save and load hotkeys.
+///
+pub const SAVE_SLOTS: u8 = 4;
+
+const SAVE_STATE_NAMES: [&str; SAVE_SLOTS as usize] = ["save1", "save2", "save3", "save4"];
+const LOAD_STATE_NAMES: [&str; SAVE_SLOTS as usize] = ["load1", "load2", "load3", "load4"];
+
+impl Action {
+    ///
+    /// Name of the action in the `[actions]` table of a bindings file,
+    /// `None` for keypad keys which live in the `[keypad]` table.
+    ///
+    pub fn name(self) -> Option<&'static str> {
+        match self {
+            Action::Keypad(_) => None,
+            Action::Quit => Some("quit"),
+            Action::Rewind => Some("rewind"),
+            Action::Debugger => Some("debugger"),
+            Action::Pause => Some("pause"),
+            Action::Step => Some("step"),
+            Action::StepOver => Some("step_over"),
+            Action::StepOut => Some("step_out"),
+            Action::Breakpoint => Some("breakpoint"),
+            Action::Memory => Some("memory"),
+            Action::SaveState(slot) => SAVE_STATE_NAMES
+                .get((slot as usize).wrapping_sub(1))
+                .copied(),
+            Action::LoadState(slot) => LOAD_STATE_NAMES
+                .get((slot as usize).wrapping_sub(1))
+                .copied(),
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        let slot = |names: &[&str]| names.iter().position(|&slot| slot == name);
+        match name {
+            "quit" => Some(Action::Quit),
+            "rewind" => Some(Action::Rewind),
+            "debugger" => Some(Action::Debugger),
+            "pause" => Some(Action::Pause),
+            "step" => Some(Action::Step),
+            "step_over" => Some(Action::StepOver),
+            "step_out" => Some(Action::StepOut),
+            "breakpoint" => Some(Action::Breakpoint),
+            "memory" => Some(Action::Memory),
+            _ => slot(&SAVE_STATE_NAMES)
+                .map(|index| Action::SaveState(index as u8 + 1))
+                .or_else(|| {
+                    slot(&LOAD_STATE_NAMES).map(|index| Action::LoadState(index as u8 + 1))
+                }),
+        }
+    }
+}
+
+///
+/// Physical keyboard layouts with a built-in preset, mapping the Chip8 keypad
+/// on the same physical keys, the left side of the keyboard:
+/// ```text
+/// | 1 | 2 | 3 | C |        | 1 | 2 | 3 | 4 |
+/// | 4 | 5 | 6 | D |   on   | Q | W | E | R |   for QWERTY
+/// | 7 | 8 | 9 | E |        | A | S | D | F |
+/// | A | 0 | B | F |        | Z | X | C | V |
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyboardLayout {
+    #[default]
+    Qwerty,
+    Azerty,
+    Qwertz,
+    Dvorak,
+}
+
+///
+/// Chip8 keys, in the order they appear on the keypad, left to right and top to bottom.
+///
+const KEYPAD_ORDER: [u8; 16] = [
+    0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+];
+
+impl KeyboardLayout {
+    // Characters of the 4x4 block on the left side of the keyboard, in keypad order.
+    // AZERTY digits need shift, the unshifted symbols are bound as well.
+    fn keys(self) -> [&'static str; 16] {
+        match self {
+            KeyboardLayout::Qwerty => [
+                "1", "2", "3", "4", "q", "w", "e", "r", "a", "s", "d", "f", "z", "x", "c", "v",
+            ],
+            KeyboardLayout::Azerty => [
+                "&", "é", "\"", "'", "a", "z", "e", "r", "q", "s", "d", "f", "w", "x", "c", "v",
+            ],
+            KeyboardLayout::Qwertz => [
+                "1", "2", "3", "4", "q", "w", "e", "r", "a", "s", "d", "f", "y", "x", "c", "v",
+            ],
+            KeyboardLayout::Dvorak => [
+                "1", "2", "3", "4", "'", ",", ".", "p", "a", "o", "e", "u", ";", "q", "j", "k",
+            ],
+        }
+    }
+
+    fn quit_keys(self) -> &'static [&'static str] {
+        match self {
+            // 'p' is part of the keypad block on Dvorak.
+            KeyboardLayout::Dvorak => &["Esc"],
+            _ => &["p", "Esc"],
+        }
+    }
+}
+
+impl fmt::Display for KeyboardLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            KeyboardLayout::Qwerty => "qwerty",
+            KeyboardLayout::Azerty => "azerty",
+            KeyboardLayout::Qwertz => "qwertz",
+            KeyboardLayout::Dvorak => "dvorak",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for KeyboardLayout {
+    type Err = BindingsError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "qwerty" => Ok(KeyboardLayout::Qwerty),
+            "azerty" => Ok(KeyboardLayout::Azerty),
+            "qwertz" => Ok(KeyboardLayout::Qwertz),
+            "dvorak" => Ok(KeyboardLayout::Dvorak),
+            _ => Err(BindingsError(format!(
+                "unknown layout '{}', expected one of: qwerty, azerty, qwertz, dvorak",
+                name
+            ))),
+        }
+    }
+}
+
+///
+/// Error raised when a bindings file or a key name cannot be understood.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindingsError(pub String);
+
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for BindingsError {}
+
+///
+/// Maps keyboard keys to emulator actions. Several keys can trigger the same action.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBindings {
+    keys: BTreeMap<Action, Vec<KeyCode>>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::preset(KeyboardLayout::default())
+    }
+}
+
+// A single key or a list of keys, both are accepted in bindings files.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            KeyList::One(key) => vec![key],
+            KeyList::Many(keys) => keys,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BindingsFile {
+    layout: Option<String>,
+    #[serde(default)]
+    keypad: BTreeMap<String, KeyList>,
+    #[serde(default)]
+    actions: BTreeMap<String, KeyList>,
+}
+
+impl KeyBindings {
+    ///
+    /// Returns the built-in bindings of the given layout.
+    ///
+    pub fn preset(layout: KeyboardLayout) -> Self {
+        let mut keys = BTreeMap::new();
+        for (&value, key) in KEYPAD_ORDER.iter().zip(layout.keys()) {
+            keys.insert(Action::Keypad(value), vec![parse_key(key).unwrap()]);
+        }
+        if layout == KeyboardLayout::Azerty {
+            for (&value, digit) in KEYPAD_ORDER.iter().zip(["1", "2", "3", "4"]) {
+                keys.get_mut(&Action::Keypad(value))
+                    .unwrap()
+                    .push(parse_key(digit).unwrap());
+            }
+        }
+        let quit_keys = layout.quit_keys().iter();
+        keys.insert(
+            Action::Quit,
+            quit_keys.map(|key| parse_key(key).unwrap()).collect(),
+        );
+        // F1 to F4 save into slots 1 to 4, F5 to F8 load them back.
+        for slot in 1..=SAVE_SLOTS {
+            keys.insert(Action::SaveState(slot), vec![KeyCode::F(slot)]);
+            keys.insert(Action::LoadState(slot), vec![KeyCode::F(SAVE_SLOTS + slot)]);
+        }
+        keys.insert(Action::Rewind, vec![KeyCode::Backspace]);
+        keys.insert(Action::Debugger, vec![KeyCode::F(9)]);
+        keys.insert(Action::Pause, vec![KeyCode::Char(' ')]);
+        keys.insert(Action::StepOver, vec![KeyCode::F(10)]);
+        keys.insert(Action::Step, vec![KeyCode::F(11)]);
+        keys.insert(Action::StepOut, vec![KeyCode::F(12)]);
+        keys.insert(Action::Breakpoint, vec![KeyCode::Char('b')]);
+        keys.insert(Action::Memory, vec![KeyCode::Tab]);
+        Self { keys }
+    }
+
+    ///
+    /// Reads bindings from a TOML document, such as:
+    /// ```toml
+    /// # Preset the bindings start from, defaults to the `default_layout` argument.
+    /// layout = "azerty"
+    ///
+    /// # Chip8 key = keyboard key(s), replacing the preset keys of each listed Chip8 key.
+    /// [keypad]
+    /// 2 = ["z", "Up"]
+    /// 8 = ["s", "Down"]
+    ///
+    /// [actions]
+    /// quit = "Esc"
+    /// save1 = "F1"
+    /// load1 = ["F5", "Home"]
+    /// ```
+    /// A keyboard key listed under two entries is rejected, as it could only trigger one of them.
+    ///
+    pub fn from_toml(
+        document: &str,
+        default_layout: KeyboardLayout,
+    ) -> Result<Self, BindingsError> {
+        let file: BindingsFile =
+            toml::from_str(document).map_err(|error| BindingsError(error.to_string()))?;
+        let layout = match file.layout {
+            Some(name) => name.parse()?,
+            None => default_layout,
+        };
+        let mut entries = Vec::new();
+        for (key, codes) in file.keypad {
+            let value = u8::from_str_radix(&key, 16)
+                .ok()
+                .filter(|&value| value < 16)
+                .ok_or_else(|| BindingsError(format!("unknown keypad key '{}'", key)))?;
+            entries.push((format!("keypad {}", key), Action::Keypad(value), codes));
+        }
+        for (name, codes) in file.actions {
+            let action = Action::from_name(&name)
+                .ok_or_else(|| BindingsError(format!("unknown action '{}'", name)))?;
+            entries.push((format!("actions {}", name), action, codes));
+        }
+        let mut bindings = Self::preset(layout);
+        let mut listed: HashMap<KeyCode, String> = HashMap::new();
+        for (entry, action, codes) in entries {
+            let codes = parse_keys(codes)?;
+            for &code in &codes {
+                match listed.insert(code, entry.clone()) {
+                    Some(other) if other != entry => {
+                        return Err(BindingsError(format!(
+                            "key '{}' is listed under both {} and {}",
+                            key_name(code),
+                            other,
+                            entry
+                        )));
+                    }
+                    _ => {}
+                }
+            }
+            bindings.bind(action, codes);
+        }
+        Ok(bindings)
+    }
+
+    ///
+    /// Replaces the keys triggering the given action.
+    /// The keys are unbound from any other action first.
+    ///
+    pub fn bind(&mut self, action: Action, codes: Vec<KeyCode>) {
+        for bound in self.keys.values_mut() {
+            bound.retain(|code| !codes.contains(code));
+        }
+        self.keys.insert(action, codes);
+    }
+
+    ///
+    /// Returns the action triggered by the given key, if any.
+    /// Letters are matched regardless of their case.
+    ///
+    pub fn action(&self, code: KeyCode) -> Option<Action> {
+        let lowercase = match code {
+            KeyCode::Char(c) => KeyCode::Char(c.to_lowercase().next().unwrap_or(c)),
+            other => other,
+        };
+        self.keys
+            .iter()
+            .find(|(_, codes)| codes.contains(&code))
+            .or_else(|| {
+                self.keys
+                    .iter()
+                    .find(|(_, codes)| codes.contains(&lowercase))
+            })
+            .map(|(&action, _)| action)
+    }
+
+    ///
+    /// Returns the keys triggering the given action.
+    ///
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.keys.get(&action).map_or(&[], Vec::as_slice)
+    }
+}
+
+fn parse_keys(keys: KeyList) -> Result<Vec<KeyCode>, BindingsError> {
+    keys.into_vec()
+        .iter()
+        .map(|key| parse_key(key).ok_or_else(|| BindingsError(format!("unknown key '{}'", key))))
+        .collect()
+}
+
+///
+/// Parses a key name: a single character, or one of `Up`, `Down`, `Left`, `Right`, `Enter`,
+/// `Esc`, `Space`, `Tab`, `Backspace`, `Delete`, `Insert`, `Home`, `End`, `PageUp`, `PageDown`
+/// and `F1` to `F12`.
+///
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    let code = match name.to_ascii_lowercase().as_str() {
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "enter" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        "space" => KeyCode::Char(' '),
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        function => {
+            let number = function.strip_prefix('f')?.parse().ok()?;
+            if !(1..=12).contains(&number) {
+                return None;
+            }
+            KeyCode::F(number)
+        }
+    };
+    Some(code)
+}
+
+///
+/// Returns the name of a key, as accepted by [`parse_key`].
+///
+pub fn key_name(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(number) => format!("F{}", number),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDown".to_string(),
+        other => format!("{:?}", other),
+    }
+}
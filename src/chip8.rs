@@ -1,8 +1,12 @@
 use crate::errors::EmulationError;
-use crate::errors::EmulationError::UnknownOpcode;
-use rand::Rng;
+use crate::instruction::{self, Instruction};
+use crate::keypad::Keypad;
+use crate::quirks::{IndexIncrement, Quirks};
+use crate::rewind::History;
+use crate::rng::Rng;
+use crate::state;
+use crate::variant::Variant;
 use std::collections::VecDeque;
-use std::convert::TryFrom;
 
 ///
 /// Initial Fonts provided by the Chip8
@@ -26,43 +30,176 @@ const FONTS_SET: [u8; 80] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
-pub(crate) struct Chip8 {
+///
+/// Large 8x10 fonts provided by the SUPER-CHIP, stored right after the small ones.
+///
+const BIG_FONTS_SET: [u8; 160] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+const BIG_FONTS_ADDRESS: usize = FONTS_SET.len();
+
+///
+/// Memory size of the Chip8 and SUPER-CHIP, in bytes.
+///
+pub const MEMORY_SIZE: usize = 0x1000;
+
+///
+/// Memory size of the XO-CHIP, in bytes.
+///
+pub const XO_CHIP_MEMORY_SIZE: usize = 0x10000;
+
+///
+/// Width of the Chip8 screen, in pixels.
+///
+pub const SCREEN_WIDTH: usize = 64;
+
+///
+/// Height of the Chip8 screen, in pixels.
+///
+pub const SCREEN_HEIGHT: usize = 32;
+
+///
+/// Width of the SUPER-CHIP high resolution screen, in pixels.
+///
+pub const HIRES_SCREEN_WIDTH: usize = 128;
+
+///
+/// Height of the SUPER-CHIP high resolution screen, in pixels.
+///
+pub const HIRES_SCREEN_HEIGHT: usize = 64;
+
+///
+/// Address at which roms are loaded, and where the program counter starts.
+///
+pub const PROGRAM_START: usize = 0x200;
+
+///
+/// Nested subroutine calls allowed by default, as on the original interpreter.
+///
+pub const DEFAULT_STACK_LIMIT: usize = 16;
+
+///
+/// The Chip8 virtual machine.
+///
+/// A rom is loaded with [`Chip8::load`], then the machine is stepped one instruction at a time
+/// with [`Chip8::cycle`], which takes the current keypad state as input. The screen can be read
+/// back at any time through [`Chip8::framebuffer`].
+///
+#[derive(Clone)]
+pub struct Chip8 {
     pub op_code: u16,
     // also named PC
     // This is where to read the op code in memory
     pub program_counter: u16,
-    pub memory: [u8; 4096],
+    // 4 KiB, or 64 KiB on XO-CHIP
+    pub memory: Vec<u8>,
     // also named V
     pub register: [u8; 16],
     pub memory_index: u16, // also named I
-    // 64x32 pixel
-    pub gfx: [bool; 2048],
+    // 64x32 pixel, or 128x64 in high resolution.
+    // Each pixel holds one bit per plane, plane 1 being the lowest bit.
+    pub gfx: Vec<u8>,
+    pub hires: bool,
+    // XO-CHIP planes affected by drawing, clearing and scrolling, as a bitmask.
+    pub selected_planes: u8,
+    // XO-CHIP 1-bit audio pattern, played at a rate controlled by the pitch register.
+    pub audio_pattern: [u8; 16],
+    pub pitch: u8,
     pub delay_timer: u8,
     pub sound_timer: u8,
+    // Whether the buzzer sounded during the last frame, the sound timer being set when it ticked.
+    pub buzzer: bool,
+    // Frames the buzzer has been sounding for in a row, locating the tone within its waveform.
+    pub sound_frames: u32,
     pub stack: Vec<u16>,
-    pub rng: rand::prelude::ThreadRng,
+    // Nested calls allowed before 2NNN fails with a stack overflow.
+    pub stack_limit: usize,
+    pub rng: Rng,
     pub should_draw: bool,
+    pub quirks: Quirks,
+    pub variant: Variant,
+    // SUPER-CHIP "RPL" user flags, saved and restored by FX75 / FX85.
+    pub rpl_flags: [u8; 16],
+    // Set once 00FD has been executed, the machine does nothing afterward.
+    pub exited: bool,
+    // Key pressed while FX0A waits, FX0A completes once it is released.
+    pub awaited_key: Option<u8>,
+    // Hash of the loaded rom, identifying the game save states belong to.
+    pub rom_hash: u64,
+    // Snapshots of the previous frames, to rewind the machine, none unless given a capacity.
+    pub history: History,
     #[cfg(debug_assertions)]
     pub debug_log: VecDeque<String>,
 }
 
 impl Default for Chip8 {
     fn default() -> Self {
-        println!("Rusty Chip8 initialized!");
-        let mut init_memory = [0; 4096];
-        init_memory[..80].clone_from_slice(&FONTS_SET);
+        Self::new(Quirks::default())
+    }
+}
+
+impl Chip8 {
+    ///
+    /// Creates a Chip8 machine running the instructions with the given quirks.
+    ///
+    pub fn new(quirks: Quirks) -> Self {
+        Self::with_variant(Variant::Chip8, quirks)
+    }
+
+    ///
+    /// Creates a machine emulating the given variant, with the given quirks.
+    ///
+    pub fn with_variant(variant: Variant, quirks: Quirks) -> Self {
+        let memory_size = match variant {
+            Variant::XoChip => XO_CHIP_MEMORY_SIZE,
+            _ => MEMORY_SIZE,
+        };
+        let mut init_memory = vec![0; memory_size];
+        init_memory[..FONTS_SET.len()].clone_from_slice(&FONTS_SET);
+        init_memory[BIG_FONTS_ADDRESS..BIG_FONTS_ADDRESS + BIG_FONTS_SET.len()]
+            .clone_from_slice(&BIG_FONTS_SET);
         Self {
             op_code: 0,
             memory: init_memory,
             register: [0; 16],
             memory_index: 0,
-            program_counter: 0x200,
-            gfx: [false; 2048],
+            program_counter: PROGRAM_START as u16,
+            gfx: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            hires: false,
+            selected_planes: 1,
+            audio_pattern: [0; 16],
+            pitch: 64,
             delay_timer: 0,
             sound_timer: 0,
+            buzzer: false,
+            sound_frames: 0,
             stack: Vec::new(),
-            rng: rand::thread_rng(),
+            stack_limit: DEFAULT_STACK_LIMIT,
+            rng: Rng::from_entropy(),
             should_draw: false,
+            quirks,
+            variant,
+            rpl_flags: [0; 16],
+            exited: false,
+            awaited_key: None,
+            rom_hash: state::hash(&[]),
+            history: History::default(),
             #[cfg(debug_assertions)]
             debug_log: VecDeque::new(),
         }
@@ -88,397 +225,570 @@ impl Chip8 {
         self.debug_log.push_back(log.to_string())
     }
 
-    pub fn load(&mut self, bytes: Vec<u8>) {
-        let mut i = 512;
-        for v in &bytes {
-            self.memory[i] = *v;
-            i = i + 1;
+    ///
+    /// Reseeds the random number generator used by CXNN, making the run reproducible.
+    ///
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    ///
+    /// Copies the rom into memory, starting at [`PROGRAM_START`].
+    /// Fails with [`EmulationError::RomTooLarge`] when it does not fit, leaving memory untouched.
+    ///
+    pub fn load(&mut self, bytes: &[u8]) -> Result<(), EmulationError> {
+        let max = self.memory.len() - PROGRAM_START;
+        if bytes.len() > max {
+            return Err(EmulationError::RomTooLarge {
+                size: bytes.len(),
+                max,
+            });
         }
+        self.memory[PROGRAM_START..PROGRAM_START + bytes.len()].copy_from_slice(bytes);
+        self.rom_hash = state::hash(bytes);
+        self.history.clear();
 
         #[cfg(debug_assertions)]
         self.log_str("Rom Loaded into memory");
+        Ok(())
     }
 
-    fn read_op_code(&self) -> u16 {
-        (self.memory[self.program_counter as usize] as u16) << 8
-            | self.memory[(self.program_counter + 1) as usize] as u16
+    ///
+    /// Returns the screen content, row by row, starting from the top left corner.
+    /// Each pixel holds one bit per plane it is lit on, plane 1 being the lowest bit:
+    /// only XO-CHIP roms use the second plane, giving four colours.
+    /// The buffer is [`Chip8::width`] by [`Chip8::height`] pixels.
+    ///
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.gfx
     }
 
-    // Register X is always located at the same position in opcode.
-    fn read_vx(&self) -> u8 {
-        self.register[((self.op_code & 0x0F00) >> 8) as usize]
+    ///
+    /// Returns whether the pixel at the given coordinates is lit on any plane,
+    /// (0, 0) being the top left corner. Pixels off the screen are never lit.
+    ///
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.planes_at(x, y) != 0
     }
 
-    // Write at vx location.
-    fn write_vx(&mut self, value: u8) {
-        let register_index = ((self.op_code & 0x0F00) >> 8) as usize;
-        self.register[register_index] = value;
+    ///
+    /// Returns the planes the pixel at the given coordinates is lit on, as a bitmask.
+    /// Pixels off the screen are lit on no plane.
+    ///
+    pub fn planes_at(&self, x: usize, y: usize) -> u8 {
+        if x < self.width() && y < self.height() {
+            self.gfx[x + y * self.width()]
+        } else {
+            0
+        }
     }
 
-    // Register Y if available is always located at the same position in opcode.
-    fn read_vy(&self) -> u8 {
-        self.register[((self.op_code & 0x00F0) >> 4) as usize]
+    ///
+    /// Width of the screen in the current resolution.
+    ///
+    pub fn width(&self) -> usize {
+        if self.hires {
+            HIRES_SCREEN_WIDTH
+        } else {
+            SCREEN_WIDTH
+        }
     }
 
-    fn write_vf(&mut self, value: u8) {
-        self.register[0x0F] = value
+    ///
+    /// Height of the screen in the current resolution.
+    ///
+    pub fn height(&self) -> usize {
+        if self.hires {
+            HIRES_SCREEN_HEIGHT
+        } else {
+            SCREEN_HEIGHT
+        }
+    }
+
+    fn set_resolution(&mut self, hires: bool) {
+        self.hires = hires;
+        self.gfx = vec![0; self.width() * self.height()];
+        self.should_draw = true;
+    }
+
+    fn clear_screen(&mut self) {
+        let planes = self.selected_planes;
+        self.gfx.iter_mut().for_each(|pixel| *pixel &= !planes);
+        self.should_draw = true;
+    }
+
+    // Moves the selected planes by the given offset, pixels scrolled in are cleared.
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = (self.width() as isize, self.height() as isize);
+        let planes = self.selected_planes;
+        let previous = self.gfx.clone();
+        for y in 0..height {
+            for x in 0..width {
+                let (source_x, source_y) = (x - dx, y - dy);
+                let source = if (0..width).contains(&source_x) && (0..height).contains(&source_y) {
+                    previous[(source_x + source_y * width) as usize] & planes
+                } else {
+                    0
+                };
+                let pixel = &mut self.gfx[(x + y * width) as usize];
+                *pixel = (*pixel & !planes) | source;
+            }
+        }
+        self.should_draw = true;
+    }
+
+    fn is_super_chip(&self) -> bool {
+        self.variant != Variant::Chip8
+    }
+
+    fn is_xo_chip(&self) -> bool {
+        self.variant == Variant::XoChip
+    }
+
+    fn unknown_opcode(&self) -> EmulationError {
+        EmulationError::UnknownOpcode {
+            pc: self.program_counter,
+            opcode: self.op_code,
+        }
+    }
+
+    // Fails unless the `length` bytes starting at `address` are all in memory.
+    fn check_memory(&self, address: usize, length: usize) -> Result<(), EmulationError> {
+        if address + length <= self.memory.len() {
+            Ok(())
+        } else {
+            Err(EmulationError::MemoryOutOfBounds {
+                address: address.max(self.memory.len()),
+                pc: self.program_counter,
+                opcode: self.op_code,
+            })
+        }
     }
 
-    fn set_program_counter(&mut self, index: u16) {
-        self.program_counter = index;
+    fn read_word(&self, address: usize) -> Result<u16, EmulationError> {
+        self.check_memory(address, 2)?;
+        Ok((self.memory[address] as u16) << 8 | self.memory[address + 1] as u16)
     }
 
+    // Called before the fetch, `op_code` still holds the previous instruction.
+    fn read_op_code(&self) -> Result<u16, EmulationError> {
+        let address = self.program_counter as usize;
+        if address + 2 > self.memory.len() {
+            return Err(EmulationError::PcOutOfRange {
+                pc: self.program_counter,
+                opcode: self.op_code,
+            });
+        }
+        Ok((self.memory[address] as u16) << 8 | self.memory[address + 1] as u16)
+    }
+
+    // Shift instructions operate on VY or VX depending on quirks.
+    fn read_shifted(&self, x: usize, y: usize) -> u8 {
+        if self.quirks.shift_uses_vy {
+            self.register[y]
+        } else {
+            self.register[x]
+        }
+    }
+
+    // Flags are written after the result, so the flag wins when X is F.
+    fn write_vf(&mut self, value: u8) {
+        self.register[0x0F] = value
+    }
+
+    // On XO-CHIP, skipping over the 4 bytes long F000 NNNN skips both of its words.
     fn increase_program_counter_if(&mut self, condition: bool) {
         if condition {
+            let next = self.program_counter as usize + 2;
+            if self.is_xo_chip() && self.read_word(next) == Ok(0xF000) {
+                self.increase_program_counter()
+            }
             self.increase_program_counter()
         }
     }
 
     fn increase_program_counter(&mut self) {
-        self.program_counter += 2;
+        self.program_counter = self.program_counter.wrapping_add(2);
     }
 
-    fn call_at(&mut self, address: u16) {
+    fn call_at(&mut self, address: u16) -> Result<(), EmulationError> {
+        if self.stack.len() >= self.stack_limit {
+            return Err(EmulationError::StackOverflow {
+                pc: self.program_counter,
+                opcode: self.op_code,
+                limit: self.stack_limit,
+            });
+        }
         self.stack.push(self.program_counter);
         self.program_counter = address;
+        Ok(())
     }
 
-    fn draw(&mut self, x: u8, y: u8, height: u8) {
+    // The starting position always wraps around the screen, while the sprite itself
+    // is either clipped or wrapped depending on quirks.
+    // On SUPER-CHIP, a height of 0 draws a 16x16 sprite in high resolution, and 8x16 in low
+    // resolution like SUPER-CHIP 1.x. XO-CHIP draws it 16x16 in both resolutions.
+    // On XO-CHIP, the sprite is drawn once per selected plane, each plane reading
+    // its own sprite data right after the previous one.
+    fn draw(&mut self, x: u8, y: u8, height: u8) -> Result<(), EmulationError> {
+        let (screen_width, screen_height) = (self.width(), self.height());
+        let (sprite_width, height) = match height {
+            0 if self.variant == Variant::SuperChip && !self.hires => (8, 16),
+            0 if self.is_super_chip() => (16, 16),
+            _ => (8, height as usize),
+        };
+        let bytes_per_row = sprite_width / 8;
+        let planes = self.selected_planes.count_ones() as usize;
+        self.check_memory(self.memory_index as usize, planes * height * bytes_per_row)?;
         self.should_draw = true;
         self.write_vf(0);
-        for y_row in 0..height {
-            let sprite = self.memory[(self.memory_index + y_row as u16) as usize];
-            for x_col in 0..8 {
-                if (sprite & (0x80 >> x_col)) > 0 {
-                    let gfx_loc: usize =
-                        (x as usize + x_col as usize + (y as usize + y_row as usize) * 64) % 2048;
-                    if self.gfx[gfx_loc] == true {
-                        self.write_vf(1)
+        let x = x as usize % screen_width;
+        let y = y as usize % screen_height;
+        let mut address = self.memory_index as usize;
+        for plane in [0x1u8, 0x2u8] {
+            if self.selected_planes & plane == 0 {
+                continue;
+            }
+            for y_row in 0..height {
+                let sprite = if bytes_per_row == 2 {
+                    (self.memory[address] as u16) << 8 | self.memory[address + 1] as u16
+                } else {
+                    (self.memory[address] as u16) << 8
+                };
+                address += bytes_per_row;
+                if self.quirks.clip_sprites && y + y_row >= screen_height {
+                    continue;
+                }
+                for x_col in 0..sprite_width {
+                    if self.quirks.clip_sprites && x + x_col >= screen_width {
+                        break;
+                    }
+                    if (sprite & (0x8000 >> x_col)) > 0 {
+                        let gfx_loc = (x + x_col) % screen_width
+                            + ((y + y_row) % screen_height) * screen_width;
+                        if self.gfx[gfx_loc] & plane != 0 {
+                            self.write_vf(1)
+                        }
+                        self.gfx[gfx_loc] ^= plane
                     }
-                    self.gfx[gfx_loc] ^= true
                 }
             }
         }
+        Ok(())
+    }
+
+    // XO-CHIP 5XY2 / 5XY3 operate on VX to VY, in reverse order when X > Y.
+    fn register_range(x: u8, y: u8) -> Vec<usize> {
+        let (x, y) = (x as usize, y as usize);
+        if x <= y {
+            (x..=y).collect()
+        } else {
+            (y..=x).rev().collect()
+        }
+    }
+
+    fn register_dump(&mut self, reg_max: usize) -> Result<(), EmulationError> {
+        let address = self.memory_index as usize;
+        self.check_memory(address, reg_max + 1)?;
+        self.memory[address..=address + reg_max].copy_from_slice(&self.register[..=reg_max]);
+        self.advance_memory_index(reg_max);
+        Ok(())
+    }
+
+    // Moves I past the registers FX55 / FX65 stored or loaded, as far as the quirks go.
+    fn advance_memory_index(&mut self, reg_max: usize) {
+        let increment = match self.quirks.index_increment {
+            IndexIncrement::None => 0,
+            IndexIncrement::ByX => reg_max as u16,
+            IndexIncrement::ByXPlusOne => reg_max as u16 + 1,
+        };
+        self.memory_index = self.memory_index.wrapping_add(increment);
+    }
+
+    fn register_load(&mut self, reg_max: usize) -> Result<(), EmulationError> {
+        let address = self.memory_index as usize;
+        self.check_memory(address, reg_max + 1)?;
+        self.register[..=reg_max].copy_from_slice(&self.memory[address..=address + reg_max]);
+        self.advance_memory_index(reg_max);
+        Ok(())
     }
 
-    fn register_dump(&mut self, reg_max: u8) {
-        for reg_index in 0..reg_max {
-            self.memory[self.memory_index as usize + reg_index as usize] =
-                self.register[reg_index as usize];
+    ///
+    /// Runs one 60Hz frame: executes up to `instructions_per_frame` instructions,
+    /// then decrements the timers once and records the machine into [`Chip8::history`].
+    ///
+    /// The frame ends early when the machine exits, or after a draw when the
+    /// `display_wait` quirk is enabled.
+    ///
+    pub fn run_frame(
+        &mut self,
+        keypad: Keypad,
+        instructions_per_frame: usize,
+    ) -> Result<(), EmulationError> {
+        self.run_frame_until(keypad, instructions_per_frame, |_| false)?;
+        Ok(())
+    }
+
+    ///
+    /// Runs one 60Hz frame like [`Chip8::run_frame`], ending it early as soon as `stop`
+    /// returns `true`. `stop` is called after each instruction.
+    ///
+    /// Returns whether `stop` ended the frame.
+    ///
+    pub fn run_frame_until(
+        &mut self,
+        keypad: Keypad,
+        instructions_per_frame: usize,
+        mut stop: impl FnMut(&Chip8) -> bool,
+    ) -> Result<bool, EmulationError> {
+        let mut stopped = false;
+        for _ in 0..instructions_per_frame {
+            if self.exited {
+                break;
+            }
+            let op_code = self.cycle(keypad)?;
+            if stop(self) {
+                stopped = true;
+                break;
+            }
+            if self.quirks.display_wait && op_code & 0xF000 == 0xD000 {
+                break;
+            }
+        }
+        self.tick_timers();
+        if self.history.capacity() > 0 {
+            self.history.record(self.save_state());
         }
-        self.memory[self.memory_index as usize + reg_max as usize] =
-            self.register[reg_max as usize];
+        Ok(stopped)
     }
 
-    fn register_load(&mut self, reg_max: u8) {
-        for reg_index in 0..reg_max as usize {
-            self.register[reg_index] = self.memory[self.memory_index as usize + reg_index];
+    ///
+    /// Decrements the delay and sound timers, to be called at 60Hz.
+    /// The buzzer sounds for the frame ending there when the sound timer is set.
+    ///
+    pub fn tick_timers(&mut self) {
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+        self.buzzer = self.sound_timer > 0;
+        self.sound_frames = if self.buzzer {
+            self.sound_frames.wrapping_add(1)
+        } else {
+            0
+        };
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
         }
-        self.register[reg_max as usize] =
-            self.memory[self.memory_index as usize + reg_max as usize];
     }
 
-    pub fn cycle(&mut self, keypad: Option<u8>) -> Result<u16, EmulationError> {
-        // Fetch Opcode
-        self.op_code = self.read_op_code();
-        // Decode Opcode
-        // Op code list : https://en.wikipedia.org/wiki/CHIP-8#Opcode_table
+    ///
+    /// Fetches, decodes and executes the instruction located at the program counter.
+    /// Timers are left untouched, see [`Chip8::run_frame`] and [`Chip8::tick_timers`].
+    ///
+    /// `keypad` holds the keys currently held down.
+    /// Returns the executed opcode. On error, the faulty instruction has no effect and the
+    /// program counter still points at it.
+    ///
+    pub fn cycle(&mut self, keypad: Keypad) -> Result<u16, EmulationError> {
+        if self.exited {
+            return Ok(self.op_code);
+        }
+        self.op_code = self.read_op_code()?;
+        let instruction = match instruction::decode(self.op_code) {
+            Ok(instruction) if instruction.is_supported_by(self.variant) => instruction,
+            _ => return Err(self.unknown_opcode()),
+        };
+        self.execute(instruction, keypad)?;
         #[cfg(debug_assertions)]
-        let mut log = format!("Reading opcode [{:#06X}]", self.op_code);
-
-        match self.op_code & 0xF000 {
-            0x0000 => match self.op_code {
-                0x00E0 => {
-                    self.gfx.fill(false);
-                    self.increase_program_counter();
-                    #[cfg(debug_assertions)]
-                    log.push_str("Clear screen")
+        self.log(format!(
+            "Reading opcode [{:#06X}] {:?}",
+            self.op_code, instruction
+        ));
+        Ok(self.op_code)
+    }
+
+    // Jumps, calls and exit set the program counter themselves, every other instruction
+    // moves it to the next one.
+    fn execute(&mut self, instruction: Instruction, keypad: Keypad) -> Result<(), EmulationError> {
+        match instruction {
+            Instruction::ClearScreen => self.clear_screen(),
+            Instruction::Return => {
+                self.program_counter = self.stack.pop().ok_or(EmulationError::StackUnderflow {
+                    pc: self.program_counter,
+                    opcode: self.op_code,
+                })?;
+            }
+            Instruction::ScrollDown { n } => self.scroll(0, n as isize),
+            Instruction::ScrollUp { n } => self.scroll(0, -(n as isize)),
+            Instruction::ScrollRight => self.scroll(4, 0),
+            Instruction::ScrollLeft => self.scroll(-4, 0),
+            Instruction::Exit => {
+                self.exited = true;
+                return Ok(());
+            }
+            Instruction::LowResolution => self.set_resolution(false),
+            Instruction::HighResolution => self.set_resolution(true),
+            Instruction::Jump { address } => {
+                self.program_counter = address;
+                return Ok(());
+            }
+            Instruction::Call { address } => return self.call_at(address),
+            Instruction::SkipIfEqual { x, value } => {
+                self.increase_program_counter_if(self.register[x as usize] == value)
+            }
+            Instruction::SkipIfNotEqual { x, value } => {
+                self.increase_program_counter_if(self.register[x as usize] != value)
+            }
+            Instruction::SkipIfRegistersEqual { x, y } => self.increase_program_counter_if(
+                self.register[x as usize] == self.register[y as usize],
+            ),
+            Instruction::StoreRange { x, y } => {
+                let registers = Self::register_range(x, y);
+                self.check_memory(self.memory_index as usize, registers.len())?;
+                for (offset, register) in registers.into_iter().enumerate() {
+                    self.memory[self.memory_index as usize + offset] = self.register[register];
                 }
-                0x00EE => {
-                    self.program_counter = self.stack.pop().unwrap();
-                    self.increase_program_counter();
-                    #[cfg(debug_assertions)]
-                    log.push_str(&format!(
-                        "set program counter from stack back to {:#06X}",
-                        self.program_counter
-                    ))
+            }
+            Instruction::LoadRange { x, y } => {
+                let registers = Self::register_range(x, y);
+                self.check_memory(self.memory_index as usize, registers.len())?;
+                for (offset, register) in registers.into_iter().enumerate() {
+                    self.register[register] = self.memory[self.memory_index as usize + offset];
                 }
-                _ => return Err(UnknownOpcode(self.op_code)),
-            },
-            0x1000 => {
-                self.set_program_counter(self.op_code & 0x0FFF);
-                #[cfg(debug_assertions)]
-                log.push_str(&format!(
-                    "set program counter to {:#06X}",
-                    self.program_counter
-                ))
-            }
-            0x2000 => {
-                self.call_at(self.op_code & 0x0FFF);
-                #[cfg(debug_assertions)]
-                log.push_str("call instruction")
-            }
-            0x3000 => {
-                self.increase_program_counter_if(self.read_vx() == (self.op_code & 0x00FF) as u8);
-                self.increase_program_counter();
-                #[cfg(debug_assertions)]
-                log.push_str("increase pc (match vx)")
             }
-            0x4000 => {
-                self.increase_program_counter_if(self.read_vx() != (self.op_code & 0x00FF) as u8);
-                self.increase_program_counter();
-                #[cfg(debug_assertions)]
-                log.push_str("increase pc (not match vx)")
+            Instruction::Load { x, value } => self.register[x as usize] = value,
+            Instruction::Add { x, value } => {
+                self.register[x as usize] = self.register[x as usize].wrapping_add(value)
             }
-            0x5000 => {
-                self.increase_program_counter_if(self.read_vx() == self.read_vy());
-                self.increase_program_counter();
-                #[cfg(debug_assertions)]
-                log.push_str("increase pc (vx == vy)")
+            Instruction::Move { x, y } => self.register[x as usize] = self.register[y as usize],
+            Instruction::Or { x, y } | Instruction::And { x, y } | Instruction::Xor { x, y } => {
+                let (vx, vy) = (self.register[x as usize], self.register[y as usize]);
+                self.register[x as usize] = match instruction {
+                    Instruction::Or { .. } => vx | vy,
+                    Instruction::And { .. } => vx & vy,
+                    _ => vx ^ vy,
+                };
+                if self.quirks.vf_reset {
+                    self.write_vf(0);
+                }
             }
-            0x6000 => {
-                self.write_vx((self.op_code & 0x00FF) as u8);
-                self.increase_program_counter();
-                #[cfg(debug_assertions)]
-                log.push_str("write vx")
-            }
-            0x7000 => {
-                let (result, _) = self
-                    .read_vx()
-                    .overflowing_add((self.op_code & 0x00FF) as u8);
-                self.write_vx(result);
-                self.increase_program_counter();
-                #[cfg(debug_assertions)]
-                log.push_str("add into write vx (no carry flag)")
-            }
-            0x8000 => {
-                match self.op_code & 0x000F {
-                    0x0000 => {
-                        self.write_vx(self.read_vy());
-                        #[cfg(debug_assertions)]
-                        log.push_str("move vy into vx")
-                    }
-                    0x0001 => {
-                        self.write_vx(self.read_vx() | self.read_vy());
-                        #[cfg(debug_assertions)]
-                        log.push_str("vx = vx or vy")
-                    }
-                    0x0002 => {
-                        self.write_vx(self.read_vx() & self.read_vy());
-                        #[cfg(debug_assertions)]
-                        log.push_str("vx = vx and vy")
-                    }
-                    0x0003 => {
-                        self.write_vx(self.read_vx() ^ self.read_vy());
-                        #[cfg(debug_assertions)]
-                        log.push_str("vx = vx xor vy")
-                    }
-                    0x0004 => {
-                        let (result, carry) = self.read_vx().overflowing_add(self.read_vy());
-                        self.write_vx(result);
-                        self.write_vf(if carry { 1 } else { 0 });
-                        #[cfg(debug_assertions)]
-                        log.push_str("vx = vx + vy (with carry flag)")
-                    }
-                    0x0005 => {
-                        let (result, carry) = self.read_vx().overflowing_sub(self.read_vy());
-                        self.write_vx(result);
-                        self.write_vf(if carry { 0 } else { 1 });
-                        #[cfg(debug_assertions)]
-                        log.push_str("vx = vx - vy (with carry)")
-                    }
-                    // TODO(switch implementation for original chip8, see https://www.reddit.com/r/EmuDev/comments/72dunw/chip8_8xy6_help/)
-                    0x0006 => {
-                        let register_value = self.read_vx();
-                        self.write_vx(register_value >> 1);
-                        self.write_vf(register_value & 0x01);
-                        #[cfg(debug_assertions)]
-                        log.push_str("vx = vx >> 1")
-                    }
-                    0x0007 => {
-                        let (result, carry) = self.read_vy().overflowing_sub(self.read_vx());
-                        self.write_vx(result);
-                        self.write_vf(if carry { 0 } else { 1 });
-                        #[cfg(debug_assertions)]
-                        log.push_str("vx = vy - vx (with carry)")
-                    }
-                    // TODO(switch implementation for original chip8, see https://www.reddit.com/r/EmuDev/comments/72dunw/chip8_8xy6_help/)
-                    0x000E => {
-                        let register_value = self.read_vx();
-                        self.write_vx(register_value << 1);
-                        self.write_vf(if register_value & 0x80 > 1 { 1 } else { 0 });
-                        #[cfg(debug_assertions)]
-                        log.push_str("vx = vx << 1")
-                    }
-                    _ => return Err(UnknownOpcode(self.op_code)),
+            Instruction::AddRegisters { x, y } => {
+                let (result, carry) =
+                    self.register[x as usize].overflowing_add(self.register[y as usize]);
+                self.register[x as usize] = result;
+                self.write_vf(if carry { 1 } else { 0 });
+            }
+            Instruction::Subtract { x, y } => {
+                let (result, borrow) =
+                    self.register[x as usize].overflowing_sub(self.register[y as usize]);
+                self.register[x as usize] = result;
+                self.write_vf(if borrow { 0 } else { 1 });
+            }
+            // see https://www.reddit.com/r/EmuDev/comments/72dunw/chip8_8xy6_help/
+            Instruction::ShiftRight { x, y } => {
+                let register_value = self.read_shifted(x as usize, y as usize);
+                self.register[x as usize] = register_value >> 1;
+                self.write_vf(register_value & 0x01);
+            }
+            Instruction::SubtractReversed { x, y } => {
+                let (result, borrow) =
+                    self.register[y as usize].overflowing_sub(self.register[x as usize]);
+                self.register[x as usize] = result;
+                self.write_vf(if borrow { 0 } else { 1 });
+            }
+            // see https://www.reddit.com/r/EmuDev/comments/72dunw/chip8_8xy6_help/
+            Instruction::ShiftLeft { x, y } => {
+                let register_value = self.read_shifted(x as usize, y as usize);
+                self.register[x as usize] = register_value << 1;
+                self.write_vf(register_value >> 7);
+            }
+            Instruction::SkipIfRegistersNotEqual { x, y } => self.increase_program_counter_if(
+                self.register[x as usize] != self.register[y as usize],
+            ),
+            Instruction::SetIndex { address } => self.memory_index = address,
+            Instruction::JumpWithOffset { address } => {
+                let offset = if self.quirks.jump_uses_vx {
+                    self.register[(address >> 8) as usize]
+                } else {
+                    self.register[0]
                 };
-                self.increase_program_counter();
+                self.program_counter = address + offset as u16;
+                return Ok(());
             }
-            0x9000 => {
-                self.increase_program_counter_if(self.read_vx() != self.read_vy());
-                self.increase_program_counter();
-                #[cfg(debug_assertions)]
-                log.push_str("increase pc vx != vy")
+            Instruction::Random { x, mask } => {
+                self.register[x as usize] = self.rng.next_u8() & mask
             }
-            0xA000 => {
-                self.memory_index = self.op_code & 0x0FFF;
-                self.increase_program_counter();
-                #[cfg(debug_assertions)]
-                log.push_str("write memory")
-            }
-            0xB000 => {
-                let v0: u16 = self.register[0] as u16;
-                self.set_program_counter((self.op_code & 0x0FFF) + v0);
-                #[cfg(debug_assertions)]
-                log.push_str(&format!("jump by {}", v0))
-            }
-            0xC000 => {
-                let random_number: u8 = self.rng.gen();
-                self.write_vx(random_number & (self.op_code & 0x00FF) as u8);
-                self.increase_program_counter();
-                #[cfg(debug_assertions)]
-                log.push_str("randomize vx")
-            }
-            0xD000 => {
-                self.draw(
-                    self.read_vx(),
-                    self.read_vy(),
-                    (self.op_code & 0x000F) as u8,
-                );
+            Instruction::Draw { x, y, height } => {
+                self.draw(self.register[x as usize], self.register[y as usize], height)?
+            }
+            Instruction::SkipIfPressed { x } => {
+                self.increase_program_counter_if(keypad.is_pressed(self.register[x as usize]))
+            }
+            Instruction::SkipIfNotPressed { x } => {
+                self.increase_program_counter_if(!keypad.is_pressed(self.register[x as usize]))
+            }
+            Instruction::SetIndexLong => {
+                self.memory_index = self.read_word(self.program_counter as usize + 2)?;
                 self.increase_program_counter();
-                #[cfg(debug_assertions)]
-                log.push_str("draw")
-            }
-            0xE000 => match self.op_code & 0x00FF {
-                0x009E => {
-                    let key_checked = self.read_vx();
-                    match keypad {
-                        Some(key_pressed) => {
-                            self.increase_program_counter_if(key_pressed == key_checked)
-                        }
-                        _ => {}
-                    };
-                    self.increase_program_counter();
-                    #[cfg(debug_assertions)]
-                    log.push_str(&format!("skip if key {} pressed in vx", key_checked))
+            }
+            Instruction::SelectPlanes { planes } => self.selected_planes = planes & 0x3,
+            Instruction::LoadAudioPattern => {
+                let address = self.memory_index as usize;
+                self.check_memory(address, 16)?;
+                self.audio_pattern
+                    .copy_from_slice(&self.memory[address..address + 16]);
+            }
+            Instruction::ReadDelay { x } => self.register[x as usize] = self.delay_timer,
+            // Stay on this instruction until a key is pressed then released.
+            Instruction::WaitKey { x } => match self.awaited_key {
+                Some(key) if !keypad.is_pressed(key) => {
+                    self.awaited_key = None;
+                    self.register[x as usize] = key;
                 }
-                0x00A1 => {
-                    let key_checked = self.read_vx();
-                    match keypad {
-                        Some(key_pressed) => {
-                            self.increase_program_counter_if(key_pressed != key_checked)
-                        }
-                        _ => {}
-                    };
-                    self.increase_program_counter();
-                    #[cfg(debug_assertions)]
-                    log.push_str(&format!("skip if key {} pressed in not vx", key_checked))
+                Some(_) => return Ok(()),
+                None => {
+                    self.awaited_key = keypad.first_pressed();
+                    return Ok(());
                 }
-                _ => return Err(UnknownOpcode(self.op_code)),
             },
-            0xF000 => {
-                match self.op_code & 0x00FF {
-                    0x0007 => {
-                        self.write_vx(self.delay_timer);
-                        #[cfg(debug_assertions)]
-                        log.push_str("vx = delay timer");
-                    }
-                    0x000A => {
-                        // Increase counter only if key press
-                        match keypad {
-                            Some(_) => {
-                                self.increase_program_counter();
-                                #[cfg(debug_assertions)]
-                                log.push_str("key pressed read, continuing")
-                            }
-                            _ =>
-                            {
-                                #[cfg(debug_assertions)]
-                                log.push_str("wait for key press")
-                            }
-                        }
-                    }
-                    0x0015 => {
-                        self.delay_timer = self.read_vx();
-                        #[cfg(debug_assertions)]
-                        log.push_str("set delay timer");
-                    }
-                    0x0018 => {
-                        self.sound_timer = self.read_vx();
-                        #[cfg(debug_assertions)]
-                        log.push_str("set sound timer");
-                    }
-                    0x001E => {
-                        let (result, _) = self.memory_index.overflowing_add(self.read_vx() as u16);
-                        self.memory_index = result & 0x0FFF; // u12, not u16
-                                                             // TODO handle overflowing.
-                        #[cfg(debug_assertions)]
-                        log.push_str("i = i + vx")
-                    }
-                    0x0029 => {
-                        self.memory_index = match self.read_vx() {
-                            0x0 => 0,
-                            0x1 => 5,
-                            0x2 => 10,
-                            0x3 => 15,
-                            0x4 => 20,
-                            0x5 => 25,
-                            0x6 => 30,
-                            0x7 => 35,
-                            0x8 => 40,
-                            _ => return Err(UnknownOpcode(self.op_code)),
-                        };
-                        #[cfg(debug_assertions)]
-                        log.push_str(&format!("i = sprite_addr[{:#06X}]", self.read_vx()))
-                    }
-                    0x0033 => {
-                        let mut value = self.read_vx();
-                        let hundreds = value / 100;
-                        value %= 100;
-                        let tens = value / 10;
-                        let unit = value % 10;
-                        self.memory[self.memory_index as usize] = hundreds;
-                        self.memory[self.memory_index as usize + 1] = tens;
-                        self.memory[self.memory_index as usize + 2] = unit;
-                        #[cfg(debug_assertions)]
-                        log.push_str(&format!(
-                            "vx {:#06X} as decimal number [{} {} {}]",
-                            self.read_vx(),
-                            hundreds,
-                            tens,
-                            unit
-                        ))
-                    }
-                    0x0055 => {
-                        let register_index = u8::try_from((self.op_code & 0x0F00) >> 8).unwrap();
-                        self.register_dump(register_index);
-                        #[cfg(debug_assertions)]
-                        log.push_str("dump vy")
-                    }
-                    0x0065 => {
-                        let register_index = u8::try_from((self.op_code & 0x0F00) >> 8).unwrap();
-                        self.register_load(register_index);
-                        #[cfg(debug_assertions)]
-                        log.push_str("load vx")
-                    }
-                    _ => return Err(UnknownOpcode(self.op_code)),
-                };
-                self.increase_program_counter();
+            Instruction::SetDelay { x } => self.delay_timer = self.register[x as usize],
+            Instruction::SetSound { x } => self.sound_timer = self.register[x as usize],
+            Instruction::AddToIndex { x } => {
+                let result = self
+                    .memory_index
+                    .wrapping_add(self.register[x as usize] as u16);
+                // u12 on Chip8 and SUPER-CHIP, u16 on XO-CHIP.
+                self.memory_index = result & (self.memory.len() - 1) as u16;
+            }
+            // Only the lowest nibble of VX is used, as on the original interpreter.
+            Instruction::Font { x } => {
+                self.memory_index = (self.register[x as usize] & 0x0F) as u16 * 5;
+            }
+            Instruction::BigFont { x } => {
+                self.memory_index =
+                    (BIG_FONTS_ADDRESS + (self.register[x as usize] & 0x0F) as usize * 10) as u16;
+            }
+            Instruction::Bcd { x } => {
+                let value = self.register[x as usize];
+                let address = self.memory_index as usize;
+                self.check_memory(address, 3)?;
+                self.memory[address] = value / 100;
+                self.memory[address + 1] = value / 10 % 10;
+                self.memory[address + 2] = value % 10;
+            }
+            Instruction::SetPitch { x } => self.pitch = self.register[x as usize],
+            Instruction::Store { x } => self.register_dump(x as usize)?,
+            Instruction::Restore { x } => self.register_load(x as usize)?,
+            Instruction::SaveFlags { x } => {
+                let register_max = x as usize;
+                self.rpl_flags[..=register_max].copy_from_slice(&self.register[..=register_max]);
+            }
+            Instruction::LoadFlags { x } => {
+                let register_max = x as usize;
+                self.register[..=register_max].copy_from_slice(&self.rpl_flags[..=register_max]);
             }
-            _ => return Err(UnknownOpcode(self.op_code)),
-        };
-
-        #[cfg(debug_assertions)]
-        self.log(log);
-
-        // Update timers
-        if self.delay_timer > 0 {
-            self.delay_timer -= 1;
-        }
-        if self.sound_timer > 0 {
-            self.sound_timer -= 1;
         }
-
-        Ok(self.op_code)
+        self.increase_program_counter();
+        Ok(())
     }
 }
@@ -0,0 +1,161 @@
+use crate::chip8::Chip8;
+use crate::errors::EmulationError;
+use crate::instruction::{decode, Instruction};
+use crate::keypad::Keypad;
+use std::collections::BTreeSet;
+
+///
+/// What the debugger lets the machine do on the next frames.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Execution {
+    /// Frames run normally, until a breakpoint is reached.
+    Running,
+    /// No instruction runs and the timers are frozen.
+    Paused,
+    /// Frames run until the subroutine called at `return_address - 2` returns,
+    /// with `depth` return addresses on the stack.
+    StepOver { return_address: u16, depth: usize },
+    /// Frames run until the current subroutine returns, leaving less than `depth`
+    /// return addresses on the stack.
+    StepOut { depth: usize },
+}
+
+///
+/// Controls the execution of a machine: pausing, stepping and PC breakpoints.
+///
+/// The machine is run through [`Debugger::run_frame`] instead of [`Chip8::run_frame`].
+/// Stepping over or out of a subroutine keeps running whole frames, timers included, until
+/// the subroutine returns.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Debugger {
+    pub breakpoints: BTreeSet<u16>,
+    execution: Execution,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self {
+            breakpoints: BTreeSet::new(),
+            execution: Execution::Running,
+        }
+    }
+}
+
+impl Debugger {
+    ///
+    /// Returns what the machine is currently allowed to do.
+    ///
+    pub fn execution(&self) -> Execution {
+        self.execution
+    }
+
+    ///
+    /// Returns whether the machine is paused.
+    ///
+    pub fn is_paused(&self) -> bool {
+        self.execution == Execution::Paused
+    }
+
+    ///
+    /// Stops running the machine, until [`Debugger::resume`] is called.
+    ///
+    pub fn pause(&mut self) {
+        self.execution = Execution::Paused;
+    }
+
+    ///
+    /// Runs the machine again, until the next breakpoint.
+    ///
+    pub fn resume(&mut self) {
+        self.execution = Execution::Running;
+    }
+
+    ///
+    /// Adds a breakpoint at the given address, or removes the one already there.
+    /// Returns whether a breakpoint is now set.
+    ///
+    pub fn toggle_breakpoint(&mut self, address: u16) -> bool {
+        if self.breakpoints.remove(&address) {
+            false
+        } else {
+            self.breakpoints.insert(address)
+        }
+    }
+
+    ///
+    /// Executes the next instruction and pauses. Timers are left untouched.
+    ///
+    pub fn step(&mut self, vm: &mut Chip8, keypad: Keypad) -> Result<(), EmulationError> {
+        self.execution = Execution::Paused;
+        vm.cycle(keypad)?;
+        Ok(())
+    }
+
+    ///
+    /// Steps over the next instruction: a subroutine call runs until it returns,
+    /// any other instruction is simply stepped.
+    ///
+    pub fn step_over(&mut self, vm: &mut Chip8, keypad: Keypad) -> Result<(), EmulationError> {
+        let address = vm.program_counter as usize;
+        let is_call = vm.memory.get(address..address + 2).is_some_and(|word| {
+            let opcode = (word[0] as u16) << 8 | word[1] as u16;
+            matches!(decode(opcode), Ok(Instruction::Call { .. }))
+        });
+        self.step(vm, keypad)?;
+        if is_call && vm.stack.last() == Some(&(address as u16)) {
+            self.execution = Execution::StepOver {
+                return_address: (address as u16).wrapping_add(2),
+                depth: vm.stack.len() - 1,
+            };
+        }
+        Ok(())
+    }
+
+    ///
+    /// Runs until the current subroutine returns. Does nothing outside of a subroutine.
+    ///
+    pub fn step_out(&mut self, vm: &Chip8) {
+        if !vm.stack.is_empty() {
+            self.execution = Execution::StepOut {
+                depth: vm.stack.len(),
+            };
+        }
+    }
+
+    ///
+    /// Runs one frame of the machine, unless it is paused.
+    ///
+    /// The frame ends early, and the machine is paused, when the program counter reaches a
+    /// breakpoint or when the subroutine being stepped over or out of returns.
+    /// Returns whether the frame ran.
+    ///
+    pub fn run_frame(
+        &mut self,
+        vm: &mut Chip8,
+        keypad: Keypad,
+        instructions_per_frame: usize,
+    ) -> Result<bool, EmulationError> {
+        let execution = self.execution;
+        if execution == Execution::Paused {
+            return Ok(false);
+        }
+        let breakpoints = &self.breakpoints;
+        let stopped = vm.run_frame_until(keypad, instructions_per_frame, |vm| {
+            let returned = match execution {
+                Execution::StepOver {
+                    return_address,
+                    depth,
+                } => vm.program_counter == return_address && vm.stack.len() == depth,
+                Execution::StepOut { depth } => vm.stack.len() < depth,
+                _ => false,
+            };
+            returned || breakpoints.contains(&vm.program_counter)
+        })?;
+        if stopped {
+            self.execution = Execution::Paused;
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,374 @@
+use crate::chip8::PROGRAM_START;
+use crate::instruction::{decode, Instruction};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+///
+/// Assembly syntax of the disassembled instructions.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Syntax {
+    /// The classic syntax of Cowgod's reference, such as `LD V1, 0x20`.
+    #[default]
+    Classic,
+    /// The syntax of the Octo assembler, such as `v1 := 0x20`.
+    Octo,
+}
+
+///
+/// Error returned when parsing an unknown syntax name.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownSyntax(pub String);
+
+impl fmt::Display for UnknownSyntax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown syntax '{}', expected one of: classic, octo",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownSyntax {}
+
+impl FromStr for Syntax {
+    type Err = UnknownSyntax;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "classic" => Ok(Syntax::Classic),
+            "octo" => Ok(Syntax::Octo),
+            _ => Err(UnknownSyntax(name.to_string())),
+        }
+    }
+}
+
+///
+/// Returns the mnemonic of an opcode in the given syntax, or `None` when the opcode is not an
+/// instruction of any variant.
+///
+/// XO-CHIP `F000 NNNN` is 4 bytes long, only its first word is described here:
+/// [`disassemble`] appends the address held by the second one.
+///
+pub fn mnemonic(opcode: u16, syntax: Syntax) -> Option<String> {
+    let instruction = decode(opcode).ok()?;
+    Some(match syntax {
+        Syntax::Classic => classic(instruction),
+        Syntax::Octo => octo(instruction),
+    })
+}
+
+fn classic(instruction: Instruction) -> String {
+    use Instruction::*;
+    match instruction {
+        ClearScreen => "CLS".to_string(),
+        Return => "RET".to_string(),
+        ScrollDown { n } => format!("SCD {}", n),
+        ScrollUp { n } => format!("SCU {}", n),
+        ScrollRight => "SCR".to_string(),
+        ScrollLeft => "SCL".to_string(),
+        Exit => "EXIT".to_string(),
+        LowResolution => "LOW".to_string(),
+        HighResolution => "HIGH".to_string(),
+        Jump { address } => format!("JP {:#05X}", address),
+        Call { address } => format!("CALL {:#05X}", address),
+        SkipIfEqual { x, value } => format!("SE V{:X}, {:#04X}", x, value),
+        SkipIfNotEqual { x, value } => format!("SNE V{:X}, {:#04X}", x, value),
+        SkipIfRegistersEqual { x, y } => format!("SE V{:X}, V{:X}", x, y),
+        StoreRange { x, y } => format!("SAVE V{:X}-V{:X}", x, y),
+        LoadRange { x, y } => format!("LOAD V{:X}-V{:X}", x, y),
+        Load { x, value } => format!("LD V{:X}, {:#04X}", x, value),
+        Add { x, value } => format!("ADD V{:X}, {:#04X}", x, value),
+        Move { x, y } => format!("LD V{:X}, V{:X}", x, y),
+        Or { x, y } => format!("OR V{:X}, V{:X}", x, y),
+        And { x, y } => format!("AND V{:X}, V{:X}", x, y),
+        Xor { x, y } => format!("XOR V{:X}, V{:X}", x, y),
+        AddRegisters { x, y } => format!("ADD V{:X}, V{:X}", x, y),
+        Subtract { x, y } => format!("SUB V{:X}, V{:X}", x, y),
+        ShiftRight { x, y } => format!("SHR V{:X}, V{:X}", x, y),
+        SubtractReversed { x, y } => format!("SUBN V{:X}, V{:X}", x, y),
+        ShiftLeft { x, y } => format!("SHL V{:X}, V{:X}", x, y),
+        SkipIfRegistersNotEqual { x, y } => format!("SNE V{:X}, V{:X}", x, y),
+        SetIndex { address } => format!("LD I, {:#05X}", address),
+        JumpWithOffset { address } => format!("JP V0, {:#05X}", address),
+        Random { x, mask } => format!("RND V{:X}, {:#04X}", x, mask),
+        Draw { x, y, height } => format!("DRW V{:X}, V{:X}, {}", x, y, height),
+        SkipIfPressed { x } => format!("SKP V{:X}", x),
+        SkipIfNotPressed { x } => format!("SKNP V{:X}", x),
+        SetIndexLong => "LD I, long".to_string(),
+        SelectPlanes { planes } => format!("PLANE {}", planes),
+        LoadAudioPattern => "AUDIO".to_string(),
+        ReadDelay { x } => format!("LD V{:X}, DT", x),
+        WaitKey { x } => format!("LD V{:X}, K", x),
+        SetDelay { x } => format!("LD DT, V{:X}", x),
+        SetSound { x } => format!("LD ST, V{:X}", x),
+        AddToIndex { x } => format!("ADD I, V{:X}", x),
+        Font { x } => format!("LD F, V{:X}", x),
+        BigFont { x } => format!("LD HF, V{:X}", x),
+        Bcd { x } => format!("LD B, V{:X}", x),
+        SetPitch { x } => format!("PITCH V{:X}", x),
+        Store { x } => format!("LD [I], V{:X}", x),
+        Restore { x } => format!("LD V{:X}, [I]", x),
+        SaveFlags { x } => format!("LD R, V{:X}", x),
+        LoadFlags { x } => format!("LD V{:X}, R", x),
+    }
+}
+
+// Octo skips the next instruction unless the condition of `if ... then` holds,
+// so conditions are the opposite of the skip ones.
+fn octo(instruction: Instruction) -> String {
+    use Instruction::*;
+    match instruction {
+        ClearScreen => "clear".to_string(),
+        Return => "return".to_string(),
+        ScrollDown { n } => format!("scroll-down {}", n),
+        ScrollUp { n } => format!("scroll-up {}", n),
+        ScrollRight => "scroll-right".to_string(),
+        ScrollLeft => "scroll-left".to_string(),
+        Exit => "exit".to_string(),
+        LowResolution => "lores".to_string(),
+        HighResolution => "hires".to_string(),
+        Jump { address } => format!("jump {:#05X}", address),
+        Call { address } => format!(":call {:#05X}", address),
+        SkipIfEqual { x, value } => format!("if v{:x} != {:#04X} then", x, value),
+        SkipIfNotEqual { x, value } => format!("if v{:x} == {:#04X} then", x, value),
+        SkipIfRegistersEqual { x, y } => format!("if v{:x} != v{:x} then", x, y),
+        StoreRange { x, y } => format!("save v{:x} - v{:x}", x, y),
+        LoadRange { x, y } => format!("load v{:x} - v{:x}", x, y),
+        Load { x, value } => format!("v{:x} := {:#04X}", x, value),
+        Add { x, value } => format!("v{:x} += {:#04X}", x, value),
+        Move { x, y } => format!("v{:x} := v{:x}", x, y),
+        Or { x, y } => format!("v{:x} |= v{:x}", x, y),
+        And { x, y } => format!("v{:x} &= v{:x}", x, y),
+        Xor { x, y } => format!("v{:x} ^= v{:x}", x, y),
+        AddRegisters { x, y } => format!("v{:x} += v{:x}", x, y),
+        Subtract { x, y } => format!("v{:x} -= v{:x}", x, y),
+        ShiftRight { x, y } => format!("v{:x} >>= v{:x}", x, y),
+        SubtractReversed { x, y } => format!("v{:x} =- v{:x}", x, y),
+        ShiftLeft { x, y } => format!("v{:x} <<= v{:x}", x, y),
+        SkipIfRegistersNotEqual { x, y } => format!("if v{:x} == v{:x} then", x, y),
+        SetIndex { address } => format!("i := {:#05X}", address),
+        JumpWithOffset { address } => format!("jump0 {:#05X}", address),
+        Random { x, mask } => format!("v{:x} := random {:#04X}", x, mask),
+        Draw { x, y, height } => format!("sprite v{:x} v{:x} {}", x, y, height),
+        SkipIfPressed { x } => format!("if v{:x} -key then", x),
+        SkipIfNotPressed { x } => format!("if v{:x} key then", x),
+        SetIndexLong => "i := long".to_string(),
+        SelectPlanes { planes } => format!("plane {}", planes),
+        LoadAudioPattern => "audio".to_string(),
+        ReadDelay { x } => format!("v{:x} := delay", x),
+        WaitKey { x } => format!("v{:x} := key", x),
+        SetDelay { x } => format!("delay := v{:x}", x),
+        SetSound { x } => format!("buzzer := v{:x}", x),
+        AddToIndex { x } => format!("i += v{:x}", x),
+        Font { x } => format!("i := hex v{:x}", x),
+        BigFont { x } => format!("i := bighex v{:x}", x),
+        Bcd { x } => format!("bcd v{:x}", x),
+        SetPitch { x } => format!("pitch := v{:x}", x),
+        Store { x } => format!("save v{:x}", x),
+        Restore { x } => format!("load v{:x}", x),
+        SaveFlags { x } => format!("saveflags v{:x}", x),
+        LoadFlags { x } => format!("loadflags v{:x}", x),
+    }
+}
+
+///
+/// Why the disassembler considers an address as the start of a code block.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Target {
+    /// Where the rom starts running.
+    Entry,
+    /// Called by 2NNN.
+    Subroutine,
+    /// Jumped to by 1NNN.
+    Jump,
+    /// Base address of a BNNN jump table.
+    JumpTable,
+}
+
+///
+/// One line of a disassembly listing.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Address of the first byte, in memory.
+    pub address: u16,
+    /// The instruction, 2 or 4 bytes long, or up to 4 bytes of data.
+    pub bytes: Vec<u8>,
+    /// Mnemonic of the instruction, or data directive.
+    pub text: String,
+    /// Whether the bytes were reached by following the control flow from the entry point.
+    pub is_code: bool,
+    /// Set when control flow lands on this address.
+    pub target: Option<Target>,
+    /// Address of the instruction listed before whose bytes this one starts inside of, when
+    /// control flow reaches into the middle of an instruction.
+    pub overlaps: Option<u16>,
+}
+
+// Data bytes per listing line.
+const DATA_PER_LINE: usize = 4;
+
+///
+/// Disassembles a rom loaded at [`PROGRAM_START`].
+///
+/// Code is told apart from data by following the control flow from the entry point: jumps,
+/// calls, skips and jump tables bases. Bytes never reached are listed as data.
+///
+pub fn disassemble(rom: &[u8], syntax: Syntax) -> Vec<Entry> {
+    let start = PROGRAM_START;
+    let end = start + rom.len();
+    let word = |address: usize| -> Option<u16> {
+        if address >= start && address + 1 < end {
+            Some((rom[address - start] as u16) << 8 | rom[address - start + 1] as u16)
+        } else {
+            None
+        }
+    };
+    let decoded = |address: usize| word(address).and_then(|opcode| decode(opcode).ok());
+    let size = |address: usize| decoded(address).map_or(2, |instruction| instruction.size());
+
+    // Instruction starts, and the reason control flow reaches them, if any.
+    let mut instructions: BTreeSet<usize> = BTreeSet::new();
+    let mut targets: BTreeMap<usize, Target> = BTreeMap::new();
+    let mut pending = vec![(start, Some(Target::Entry))];
+    while let Some((address, target)) = pending.pop() {
+        if let Some(target) = target {
+            let known = targets.entry(address).or_insert(target);
+            *known = (*known).min(target);
+        }
+        if instructions.contains(&address) {
+            continue;
+        }
+        let instruction = match decoded(address) {
+            Some(Instruction::SetIndexLong) if word(address + 2).is_none() => continue,
+            Some(instruction) => instruction,
+            None => continue,
+        };
+        instructions.insert(address);
+        let next = address + instruction.size();
+        match instruction {
+            Instruction::Jump { address } => pending.push((address as usize, Some(Target::Jump))),
+            Instruction::Call { address } => {
+                pending.push((address as usize, Some(Target::Subroutine)));
+                pending.push((next, None));
+            }
+            Instruction::JumpWithOffset { address } => {
+                pending.push((address as usize, Some(Target::JumpTable)))
+            }
+            Instruction::Return | Instruction::Exit => {}
+            _ if instruction.is_skip() => {
+                pending.push((next, None));
+                pending.push((next + size(next), None));
+            }
+            _ => pending.push((next, None)),
+        }
+    }
+
+    let code = |address: usize, overlaps: Option<u16>| {
+        let length = size(address);
+        let opcode = word(address).unwrap_or_default();
+        let mut text = mnemonic(opcode, syntax).unwrap_or_default();
+        if opcode == 0xF000 {
+            text.push_str(&format!(" {:#06X}", word(address + 2).unwrap_or_default()));
+        }
+        Entry {
+            address: address as u16,
+            bytes: rom[address - start..address - start + length].to_vec(),
+            text,
+            is_code: true,
+            target: targets.get(&address).copied(),
+            overlaps,
+        }
+    };
+    let mut entries = Vec::new();
+    let mut address = start;
+    while address < end {
+        let target = targets.get(&address).copied();
+        if instructions.contains(&address) {
+            let length = size(address);
+            entries.push(code(address, None));
+            // Instructions starting inside this one get a line of their own.
+            for &inner in instructions.range(address + 1..address + length) {
+                entries.push(code(inner, Some(address as u16)));
+            }
+            address += length;
+        } else {
+            let length = (address..end)
+                .take(DATA_PER_LINE)
+                .take_while(|&data| data == address || !instructions.contains(&data))
+                .count();
+            let bytes = rom[address - start..address - start + length].to_vec();
+            entries.push(Entry {
+                address: address as u16,
+                text: data_directive(&bytes, syntax),
+                bytes,
+                is_code: false,
+                target,
+                overlaps: None,
+            });
+            address += length;
+        }
+    }
+    entries
+}
+
+fn data_directive(bytes: &[u8], syntax: Syntax) -> String {
+    let values: Vec<String> = bytes.iter().map(|byte| format!("{:#04X}", byte)).collect();
+    match syntax {
+        Syntax::Classic => format!("DB {}", values.join(", ")),
+        Syntax::Octo => values.join(" "),
+    }
+}
+
+///
+/// Formats entries as a listing with address, raw bytes and mnemonic columns.
+/// Addresses control flow lands on, and instructions overlapping the previous one, are
+/// annotated with a comment.
+///
+pub fn listing(entries: &[Entry], syntax: Syntax) -> String {
+    let comment = match syntax {
+        Syntax::Classic => ';',
+        Syntax::Octo => '#',
+    };
+    let mut listing = String::new();
+    for entry in entries {
+        let bytes: Vec<String> = entry
+            .bytes
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+        let mut line = format!(
+            "{:04X}  {:<11}  {}",
+            entry.address,
+            bytes.join(" "),
+            entry.text
+        );
+        let mut notes: Vec<String> = entry
+            .target
+            .iter()
+            .map(|target| {
+                match target {
+                    Target::Entry => "entry point",
+                    Target::Subroutine => "subroutine",
+                    Target::Jump => "jump target",
+                    Target::JumpTable => "jump table",
+                }
+                .to_string()
+            })
+            .collect();
+        if let Some(outer) = entry.overlaps {
+            notes.push(format!("overlaps {:04X}", outer));
+        }
+        if !notes.is_empty() {
+            line = format!("{:<48}{} {}", line, comment, notes.join(", "));
+        }
+        listing.push_str(line.trim_end());
+        listing.push('\n');
+    }
+    listing
+}
@@ -1,4 +1,69 @@
-#[derive(Debug)]
+use std::fmt;
+
+///
+/// Error raised when the machine cannot go on, leaving its state untouched by the faulty
+/// instruction so it can be inspected.
+///
+/// Errors raised while executing an instruction carry the address of the instruction (`pc`)
+/// and its `opcode`.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EmulationError {
-    UnknownOpcode(u16),
+    /// The opcode is not an instruction of the emulated variant.
+    UnknownOpcode { pc: u16, opcode: u16 },
+    /// 00EE returned while the stack was empty.
+    StackUnderflow { pc: u16, opcode: u16 },
+    /// 2NNN called a subroutine while the stack already held `limit` return addresses.
+    StackOverflow { pc: u16, opcode: u16, limit: usize },
+    /// The instruction accessed memory at `address`, past the end of the memory.
+    MemoryOutOfBounds {
+        address: usize,
+        pc: u16,
+        opcode: u16,
+    },
+    /// The program counter left the memory, `opcode` being the last instruction executed.
+    PcOutOfRange { pc: u16, opcode: u16 },
+    /// The rom does not fit in the memory available from the program start.
+    RomTooLarge { size: usize, max: usize },
 }
+
+impl fmt::Display for EmulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            EmulationError::UnknownOpcode { pc, opcode } => {
+                write!(f, "unknown opcode {:#06X} at {:#06X}", opcode, pc)
+            }
+            EmulationError::StackUnderflow { pc, opcode } => write!(
+                f,
+                "stack underflow: {:#06X} at {:#06X} returned with an empty stack",
+                opcode, pc
+            ),
+            EmulationError::StackOverflow { pc, opcode, limit } => write!(
+                f,
+                "stack overflow: {:#06X} at {:#06X} exceeded {} nested calls",
+                opcode, pc, limit
+            ),
+            EmulationError::MemoryOutOfBounds {
+                address,
+                pc,
+                opcode,
+            } => write!(
+                f,
+                "memory access out of bounds: {:#06X} at {:#06X} accessed {:#06X}",
+                opcode, pc, address
+            ),
+            EmulationError::PcOutOfRange { pc, opcode } => write!(
+                f,
+                "program counter out of range: {:#06X} reached {:#06X}",
+                opcode, pc
+            ),
+            EmulationError::RomTooLarge { size, max } => write!(
+                f,
+                "rom too large: {} bytes, at most {} bytes fit in memory",
+                size, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EmulationError {}
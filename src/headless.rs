@@ -0,0 +1,179 @@
+use crate::audio::AudioSink;
+use crate::chip8::Chip8;
+use crate::errors::EmulationError;
+use crate::keypad::Keypad;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+///
+/// Error returned when an input timeline cannot be parsed, at the given 1-based line.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineError {
+    pub line: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for TimelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid input timeline at line {}: {}",
+            self.line, self.reason
+        )
+    }
+}
+
+impl std::error::Error for TimelineError {}
+
+///
+/// Keys held down along a scripted run, changing at given frames.
+///
+/// The text form holds one `FRAME KEYS` line per change, frames counted from 0 and increasing:
+/// `KEYS` lists the hexadecimal keys held down from that frame on, or `-` for none.
+/// Comments start with `#`.
+///
+/// ```
+/// use rusty_chip8::headless::InputTimeline;
+///
+/// let timeline: InputTimeline = "60 5\n65 -  # release\n90 4a".parse().unwrap();
+/// assert!(timeline.keypad(62).is_pressed(0x5));
+/// assert_eq!(timeline.keypad(70).bits(), 0);
+/// assert_eq!(timeline.keypad(90).pressed().collect::<Vec<_>>(), [0x4, 0xA]);
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct InputTimeline {
+    // Frame of each change, increasing, with the keys held down from then on.
+    changes: Vec<(usize, Keypad)>,
+}
+
+impl InputTimeline {
+    ///
+    /// Returns the keys held down during the given frame.
+    ///
+    pub fn keypad(&self, frame: usize) -> Keypad {
+        let changes = self.changes.partition_point(|&(start, _)| start <= frame);
+        changes
+            .checked_sub(1)
+            .map_or(Keypad::default(), |change| self.changes[change].1)
+    }
+}
+
+impl FromStr for InputTimeline {
+    type Err = TimelineError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut changes: Vec<(usize, Keypad)> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let error = |reason: &str| TimelineError {
+                line: index + 1,
+                reason: reason.to_string(),
+            };
+            let line = line.split('#').next().unwrap_or_default();
+            let mut fields = line.split_whitespace();
+            let (frame, keys) = match (fields.next(), fields.next(), fields.next()) {
+                (None, _, _) => continue,
+                (Some(frame), Some(keys), None) => (frame, keys),
+                _ => return Err(error("expected a frame and the keys held down")),
+            };
+            let frame: usize = frame.parse().map_err(|_| error("invalid frame"))?;
+            if changes.last().is_some_and(|&(last, _)| last >= frame) {
+                return Err(error("frames must be increasing"));
+            }
+            let mut keypad = Keypad::default();
+            if keys != "-" {
+                for key in keys.chars() {
+                    let key = key.to_digit(16).ok_or_else(|| error("invalid key"))?;
+                    keypad.press(key as u8);
+                }
+            }
+            changes.push((frame, keypad));
+        }
+        Ok(Self { changes })
+    }
+}
+
+///
+/// Runs up to `frames` frames, feeding the keys of the timeline, and returns the number of
+/// frames run. The sound of each frame goes to `audio`.
+///
+/// The run ends early when the machine exits, or at the instruction where the program counter
+/// reaches `until_pc`.
+///
+pub fn run(
+    vm: &mut Chip8,
+    timeline: &InputTimeline,
+    frames: usize,
+    instructions_per_frame: usize,
+    until_pc: Option<u16>,
+    audio: &mut dyn AudioSink,
+) -> Result<usize, EmulationError> {
+    for frame in 0..frames {
+        if vm.exited {
+            return Ok(frame);
+        }
+        let stopped = vm.run_frame_until(timeline.keypad(frame), instructions_per_frame, |vm| {
+            Some(vm.program_counter) == until_pc
+        })?;
+        audio.frame(vm);
+        if stopped {
+            return Ok(frame + 1);
+        }
+    }
+    Ok(frames)
+}
+
+///
+/// Encodes the screen as a binary PBM image, lit pixels being black.
+///
+pub fn pbm(vm: &Chip8) -> Vec<u8> {
+    let (width, height) = (vm.width(), vm.height());
+    let mut image = format!("P4\n{} {}\n", width, height).into_bytes();
+    for row in vm.framebuffer().chunks(width) {
+        for pixels in row.chunks(8) {
+            let byte = pixels.iter().enumerate().fold(0u8, |byte, (bit, &pixel)| {
+                byte | ((pixel != 0) as u8) << (7 - bit)
+            });
+            image.push(byte);
+        }
+    }
+    image
+}
+
+// Black background, then the colours of the pixels lit on plane 1, plane 2 and both planes.
+const PNG_PALETTE: [u8; 12] = [
+    0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0x55, 0x55, 0xFF, 0xFF, 0x00,
+];
+
+///
+/// Writes the screen as an indexed PNG image, one image pixel per screen pixel.
+///
+pub fn write_png(vm: &Chip8, writer: impl Write) -> Result<(), png::EncodingError> {
+    let mut encoder = png::Encoder::new(writer, vm.width() as u32, vm.height() as u32);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(&PNG_PALETTE[..]);
+    let mut writer = encoder.write_header()?;
+    let pixels: Vec<u8> = vm.framebuffer().iter().map(|&pixel| pixel & 0x3).collect();
+    writer.write_image_data(&pixels)
+}
+
+///
+/// Draws the screen as text, one line per row: `.` for unlit pixels, `#` for pixels lit on
+/// plane 1, `+` on plane 2 and `@` on both.
+///
+pub fn ascii(vm: &Chip8) -> String {
+    let mut text = String::with_capacity((vm.width() + 1) * vm.height());
+    for row in vm.framebuffer().chunks(vm.width()) {
+        text.extend(row.iter().map(|&pixel| match pixel & 0x3 {
+            0 => '.',
+            1 => '#',
+            2 => '+',
+            _ => '@',
+        }));
+        text.push('\n');
+    }
+    text
+}
@@ -0,0 +1,304 @@
+use crate::errors::EmulationError;
+use crate::variant::Variant;
+
+///
+/// A decoded instruction, covering the instruction sets of every variant.
+///
+/// `x` and `y` are register indexes, from 0 to 15.
+/// Op code list : <https://en.wikipedia.org/wiki/CHIP-8#Opcode_table>
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// 00E0: clears the selected planes of the screen.
+    ClearScreen,
+    /// 00EE: returns from a subroutine.
+    Return,
+    /// 00CN: scrolls the screen down by `n` pixels. SUPER-CHIP.
+    ScrollDown { n: u8 },
+    /// 00DN: scrolls the screen up by `n` pixels. XO-CHIP.
+    ScrollUp { n: u8 },
+    /// 00FB: scrolls the screen right by 4 pixels. SUPER-CHIP.
+    ScrollRight,
+    /// 00FC: scrolls the screen left by 4 pixels. SUPER-CHIP.
+    ScrollLeft,
+    /// 00FD: stops the machine. SUPER-CHIP.
+    Exit,
+    /// 00FE: switches to the low resolution screen. SUPER-CHIP.
+    LowResolution,
+    /// 00FF: switches to the high resolution screen. SUPER-CHIP.
+    HighResolution,
+    /// 1NNN: jumps to `address`.
+    Jump { address: u16 },
+    /// 2NNN: calls the subroutine at `address`.
+    Call { address: u16 },
+    /// 3XNN: skips the next instruction if VX equals `value`.
+    SkipIfEqual { x: u8, value: u8 },
+    /// 4XNN: skips the next instruction if VX differs from `value`.
+    SkipIfNotEqual { x: u8, value: u8 },
+    /// 5XY0: skips the next instruction if VX equals VY.
+    SkipIfRegistersEqual { x: u8, y: u8 },
+    /// 5XY2: stores VX to VY in memory starting at I. XO-CHIP.
+    StoreRange { x: u8, y: u8 },
+    /// 5XY3: loads VX to VY from memory starting at I. XO-CHIP.
+    LoadRange { x: u8, y: u8 },
+    /// 6XNN: sets VX to `value`.
+    Load { x: u8, value: u8 },
+    /// 7XNN: adds `value` to VX, without carry flag.
+    Add { x: u8, value: u8 },
+    /// 8XY0: sets VX to VY.
+    Move { x: u8, y: u8 },
+    /// 8XY1: sets VX to VX or VY.
+    Or { x: u8, y: u8 },
+    /// 8XY2: sets VX to VX and VY.
+    And { x: u8, y: u8 },
+    /// 8XY3: sets VX to VX xor VY.
+    Xor { x: u8, y: u8 },
+    /// 8XY4: adds VY to VX, VF holding the carry.
+    AddRegisters { x: u8, y: u8 },
+    /// 8XY5: subtracts VY from VX, VF holding the absence of borrow.
+    Subtract { x: u8, y: u8 },
+    /// 8XY6: shifts VX, or VY depending on quirks, right into VX, VF holding the shifted bit.
+    ShiftRight { x: u8, y: u8 },
+    /// 8XY7: sets VX to VY minus VX, VF holding the absence of borrow.
+    SubtractReversed { x: u8, y: u8 },
+    /// 8XYE: shifts VX, or VY depending on quirks, left into VX, VF holding the shifted bit.
+    ShiftLeft { x: u8, y: u8 },
+    /// 9XY0: skips the next instruction if VX differs from VY.
+    SkipIfRegistersNotEqual { x: u8, y: u8 },
+    /// ANNN: sets I to `address`.
+    SetIndex { address: u16 },
+    /// BNNN: jumps to `address` plus V0, or plus VX depending on quirks.
+    JumpWithOffset { address: u16 },
+    /// CXNN: sets VX to a random number masked by `mask`.
+    Random { x: u8, mask: u8 },
+    /// DXYN: draws a sprite of `height` rows at VX, VY.
+    Draw { x: u8, y: u8, height: u8 },
+    /// EX9E: skips the next instruction if the key in VX is pressed.
+    SkipIfPressed { x: u8 },
+    /// EXA1: skips the next instruction if the key in VX is not pressed.
+    SkipIfNotPressed { x: u8 },
+    /// F000 NNNN: sets I to the 16 bits address in the next word. XO-CHIP.
+    SetIndexLong,
+    /// FN01: selects the planes to draw on. XO-CHIP.
+    SelectPlanes { planes: u8 },
+    /// F002: loads the 16 bytes audio pattern at I. XO-CHIP.
+    LoadAudioPattern,
+    /// FX07: sets VX to the delay timer.
+    ReadDelay { x: u8 },
+    /// FX0A: waits for a key press and release, storing the key in VX.
+    WaitKey { x: u8 },
+    /// FX15: sets the delay timer to VX.
+    SetDelay { x: u8 },
+    /// FX18: sets the sound timer to VX.
+    SetSound { x: u8 },
+    /// FX1E: adds VX to I.
+    AddToIndex { x: u8 },
+    /// FX29: points I to the small font sprite of the digit in VX.
+    Font { x: u8 },
+    /// FX30: points I to the big font sprite of the digit in VX. SUPER-CHIP.
+    BigFont { x: u8 },
+    /// FX33: stores the decimal digits of VX at I.
+    Bcd { x: u8 },
+    /// FX3A: sets the audio pitch to VX. XO-CHIP.
+    SetPitch { x: u8 },
+    /// FX55: stores V0 to VX in memory starting at I.
+    Store { x: u8 },
+    /// FX65: loads V0 to VX from memory starting at I.
+    Restore { x: u8 },
+    /// FX75: saves V0 to VX into the RPL user flags. SUPER-CHIP.
+    SaveFlags { x: u8 },
+    /// FX85: loads V0 to VX from the RPL user flags. SUPER-CHIP.
+    LoadFlags { x: u8 },
+}
+
+///
+/// Decodes an opcode into the instruction it stands for, in any variant.
+///
+/// The opcode not being located in memory, an [`EmulationError::UnknownOpcode`] reports a `pc`
+/// of 0. [`Instruction::SetIndexLong`] only stands for the first word of F000 NNNN, its address
+/// being read from the next word when executed.
+///
+pub fn decode(opcode: u16) -> Result<Instruction, EmulationError> {
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+    let nn = (opcode & 0x00FF) as u8;
+    let nnn = opcode & 0x0FFF;
+    let instruction = match (opcode & 0xF000, opcode) {
+        (_, 0x00E0) => Instruction::ClearScreen,
+        (_, 0x00EE) => Instruction::Return,
+        (_, 0x00C1..=0x00CF) => Instruction::ScrollDown { n },
+        (_, 0x00D0..=0x00DF) => Instruction::ScrollUp { n },
+        (_, 0x00FB) => Instruction::ScrollRight,
+        (_, 0x00FC) => Instruction::ScrollLeft,
+        (_, 0x00FD) => Instruction::Exit,
+        (_, 0x00FE) => Instruction::LowResolution,
+        (_, 0x00FF) => Instruction::HighResolution,
+        (0x1000, _) => Instruction::Jump { address: nnn },
+        (0x2000, _) => Instruction::Call { address: nnn },
+        (0x3000, _) => Instruction::SkipIfEqual { x, value: nn },
+        (0x4000, _) => Instruction::SkipIfNotEqual { x, value: nn },
+        (0x5000, _) if n == 0x0 => Instruction::SkipIfRegistersEqual { x, y },
+        (0x5000, _) if n == 0x2 => Instruction::StoreRange { x, y },
+        (0x5000, _) if n == 0x3 => Instruction::LoadRange { x, y },
+        (0x6000, _) => Instruction::Load { x, value: nn },
+        (0x7000, _) => Instruction::Add { x, value: nn },
+        (0x8000, _) => match n {
+            0x0 => Instruction::Move { x, y },
+            0x1 => Instruction::Or { x, y },
+            0x2 => Instruction::And { x, y },
+            0x3 => Instruction::Xor { x, y },
+            0x4 => Instruction::AddRegisters { x, y },
+            0x5 => Instruction::Subtract { x, y },
+            0x6 => Instruction::ShiftRight { x, y },
+            0x7 => Instruction::SubtractReversed { x, y },
+            0xE => Instruction::ShiftLeft { x, y },
+            _ => return Err(unknown(opcode)),
+        },
+        (0x9000, _) if n == 0x0 => Instruction::SkipIfRegistersNotEqual { x, y },
+        (0xA000, _) => Instruction::SetIndex { address: nnn },
+        (0xB000, _) => Instruction::JumpWithOffset { address: nnn },
+        (0xC000, _) => Instruction::Random { x, mask: nn },
+        (0xD000, _) => Instruction::Draw { x, y, height: n },
+        (0xE000, _) if nn == 0x9E => Instruction::SkipIfPressed { x },
+        (0xE000, _) if nn == 0xA1 => Instruction::SkipIfNotPressed { x },
+        (_, 0xF000) => Instruction::SetIndexLong,
+        (_, 0xF002) => Instruction::LoadAudioPattern,
+        (0xF000, _) => match nn {
+            0x01 => Instruction::SelectPlanes { planes: x },
+            0x07 => Instruction::ReadDelay { x },
+            0x0A => Instruction::WaitKey { x },
+            0x15 => Instruction::SetDelay { x },
+            0x18 => Instruction::SetSound { x },
+            0x1E => Instruction::AddToIndex { x },
+            0x29 => Instruction::Font { x },
+            0x30 => Instruction::BigFont { x },
+            0x33 => Instruction::Bcd { x },
+            0x3A => Instruction::SetPitch { x },
+            0x55 => Instruction::Store { x },
+            0x65 => Instruction::Restore { x },
+            0x75 => Instruction::SaveFlags { x },
+            0x85 => Instruction::LoadFlags { x },
+            _ => return Err(unknown(opcode)),
+        },
+        _ => return Err(unknown(opcode)),
+    };
+    Ok(instruction)
+}
+
+fn unknown(opcode: u16) -> EmulationError {
+    EmulationError::UnknownOpcode { pc: 0, opcode }
+}
+
+///
+/// Encodes an instruction back into its opcode, the reverse of [`decode`].
+///
+pub fn encode(instruction: &Instruction) -> u16 {
+    let xy = |x: u8, y: u8, n: u16| (x as u16 & 0xF) << 8 | (y as u16 & 0xF) << 4 | n;
+    let xnn = |x: u8, nn: u8| (x as u16 & 0xF) << 8 | nn as u16;
+    match *instruction {
+        Instruction::ClearScreen => 0x00E0,
+        Instruction::Return => 0x00EE,
+        Instruction::ScrollDown { n } => 0x00C0 | (n as u16 & 0xF),
+        Instruction::ScrollUp { n } => 0x00D0 | (n as u16 & 0xF),
+        Instruction::ScrollRight => 0x00FB,
+        Instruction::ScrollLeft => 0x00FC,
+        Instruction::Exit => 0x00FD,
+        Instruction::LowResolution => 0x00FE,
+        Instruction::HighResolution => 0x00FF,
+        Instruction::Jump { address } => 0x1000 | (address & 0x0FFF),
+        Instruction::Call { address } => 0x2000 | (address & 0x0FFF),
+        Instruction::SkipIfEqual { x, value } => 0x3000 | xnn(x, value),
+        Instruction::SkipIfNotEqual { x, value } => 0x4000 | xnn(x, value),
+        Instruction::SkipIfRegistersEqual { x, y } => 0x5000 | xy(x, y, 0x0),
+        Instruction::StoreRange { x, y } => 0x5000 | xy(x, y, 0x2),
+        Instruction::LoadRange { x, y } => 0x5000 | xy(x, y, 0x3),
+        Instruction::Load { x, value } => 0x6000 | xnn(x, value),
+        Instruction::Add { x, value } => 0x7000 | xnn(x, value),
+        Instruction::Move { x, y } => 0x8000 | xy(x, y, 0x0),
+        Instruction::Or { x, y } => 0x8000 | xy(x, y, 0x1),
+        Instruction::And { x, y } => 0x8000 | xy(x, y, 0x2),
+        Instruction::Xor { x, y } => 0x8000 | xy(x, y, 0x3),
+        Instruction::AddRegisters { x, y } => 0x8000 | xy(x, y, 0x4),
+        Instruction::Subtract { x, y } => 0x8000 | xy(x, y, 0x5),
+        Instruction::ShiftRight { x, y } => 0x8000 | xy(x, y, 0x6),
+        Instruction::SubtractReversed { x, y } => 0x8000 | xy(x, y, 0x7),
+        Instruction::ShiftLeft { x, y } => 0x8000 | xy(x, y, 0xE),
+        Instruction::SkipIfRegistersNotEqual { x, y } => 0x9000 | xy(x, y, 0x0),
+        Instruction::SetIndex { address } => 0xA000 | (address & 0x0FFF),
+        Instruction::JumpWithOffset { address } => 0xB000 | (address & 0x0FFF),
+        Instruction::Random { x, mask } => 0xC000 | xnn(x, mask),
+        Instruction::Draw { x, y, height } => 0xD000 | xy(x, y, height as u16 & 0xF),
+        Instruction::SkipIfPressed { x } => 0xE09E | xnn(x, 0),
+        Instruction::SkipIfNotPressed { x } => 0xE0A1 | xnn(x, 0),
+        Instruction::SetIndexLong => 0xF000,
+        Instruction::SelectPlanes { planes } => 0xF001 | xnn(planes, 0),
+        Instruction::LoadAudioPattern => 0xF002,
+        Instruction::ReadDelay { x } => 0xF007 | xnn(x, 0),
+        Instruction::WaitKey { x } => 0xF00A | xnn(x, 0),
+        Instruction::SetDelay { x } => 0xF015 | xnn(x, 0),
+        Instruction::SetSound { x } => 0xF018 | xnn(x, 0),
+        Instruction::AddToIndex { x } => 0xF01E | xnn(x, 0),
+        Instruction::Font { x } => 0xF029 | xnn(x, 0),
+        Instruction::BigFont { x } => 0xF030 | xnn(x, 0),
+        Instruction::Bcd { x } => 0xF033 | xnn(x, 0),
+        Instruction::SetPitch { x } => 0xF03A | xnn(x, 0),
+        Instruction::Store { x } => 0xF055 | xnn(x, 0),
+        Instruction::Restore { x } => 0xF065 | xnn(x, 0),
+        Instruction::SaveFlags { x } => 0xF075 | xnn(x, 0),
+        Instruction::LoadFlags { x } => 0xF085 | xnn(x, 0),
+    }
+}
+
+impl Instruction {
+    ///
+    /// Returns whether the instruction belongs to the instruction set of the given variant.
+    ///
+    pub fn is_supported_by(&self, variant: Variant) -> bool {
+        match self {
+            Instruction::ScrollDown { .. }
+            | Instruction::ScrollRight
+            | Instruction::ScrollLeft
+            | Instruction::Exit
+            | Instruction::LowResolution
+            | Instruction::HighResolution
+            | Instruction::BigFont { .. }
+            | Instruction::SaveFlags { .. }
+            | Instruction::LoadFlags { .. } => variant != Variant::Chip8,
+            Instruction::ScrollUp { .. }
+            | Instruction::StoreRange { .. }
+            | Instruction::LoadRange { .. }
+            | Instruction::SetIndexLong
+            | Instruction::SelectPlanes { .. }
+            | Instruction::LoadAudioPattern
+            | Instruction::SetPitch { .. } => variant == Variant::XoChip,
+            _ => true,
+        }
+    }
+
+    ///
+    /// Returns whether the instruction may skip the next one.
+    ///
+    pub fn is_skip(&self) -> bool {
+        matches!(
+            self,
+            Instruction::SkipIfEqual { .. }
+                | Instruction::SkipIfNotEqual { .. }
+                | Instruction::SkipIfRegistersEqual { .. }
+                | Instruction::SkipIfRegistersNotEqual { .. }
+                | Instruction::SkipIfPressed { .. }
+                | Instruction::SkipIfNotPressed { .. }
+        )
+    }
+
+    ///
+    /// Length of the instruction in memory, in bytes: 4 for F000 NNNN, 2 otherwise.
+    ///
+    pub fn size(&self) -> usize {
+        if *self == Instruction::SetIndexLong {
+            4
+        } else {
+            2
+        }
+    }
+}
@@ -1,78 +1,372 @@
-use crate::keypad::KeypadEvent::{Clear, Quit};
+use crate::bindings::{Action, KeyBindings};
+use crate::keypad::KeypadEvent::{Hotkey, HotkeyRelease, Input, Press, Quit, Release};
 use async_std::channel::Sender;
 use async_std::task::JoinHandle;
 use crossterm::event::Event::Key;
-use crossterm::event::{EventStream, KeyCode, KeyEventKind};
+use crossterm::event::{
+    EventStream, KeyCode, KeyEventKind, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
+    PushKeyboardEnhancementFlags,
+};
+use crossterm::terminal::supports_keyboard_enhancement;
+use crossterm::ExecutableCommand;
 use futures::{future::FutureExt, select, StreamExt};
 use futures_timer::Delay;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, stdout};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 ///
-/// Read keypad state, but only block read until timeout is reached. if timeout is reached,
-/// it means that no keys have been input.
+/// Default delay after which a key is considered released when the terminal does not report
+/// key releases. It must be longer than the keyboard auto-repeat delay, otherwise held keys
+/// flicker between pressed and released until the auto-repeat kicks in.
+///
+pub const DEFAULT_RELEASE_TIMEOUT: Duration = Duration::from_millis(700);
+
+///
+/// How the keypad handler knows that a key is not held down anymore.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReleaseDetection {
+    /// The terminal reports genuine release events (kitty keyboard protocol).
+    Reported,
+    /// The terminal only reports presses, a key is released once it has not been
+    /// pressed (or auto-repeated) for the given duration.
+    Timeout(Duration),
+}
+
+impl fmt::Display for ReleaseDetection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReleaseDetection::Reported => write!(f, "key releases reported by the terminal"),
+            ReleaseDetection::Timeout(timeout) => {
+                write!(f, "keys auto-released after {}ms", timeout.as_millis())
+            }
+        }
+    }
+}
+
+impl ReleaseDetection {
+    ///
+    /// Asks the terminal to report key releases when it supports it,
+    /// falling back to releasing keys after `timeout` otherwise.
+    /// Must be called once the terminal is in raw mode.
+    ///
+    pub fn enable(timeout: Duration) -> Self {
+        let supported = matches!(supports_keyboard_enhancement(), Ok(true))
+            && stdout()
+                .execute(PushKeyboardEnhancementFlags(
+                    KeyboardEnhancementFlags::REPORT_EVENT_TYPES,
+                ))
+                .is_ok();
+        if supported {
+            ReleaseDetection::Reported
+        } else {
+            ReleaseDetection::Timeout(timeout)
+        }
+    }
+
+    ///
+    /// Restores the terminal keyboard reporting changed by [`ReleaseDetection::enable`].
+    ///
+    pub fn disable(self) -> io::Result<()> {
+        if self == ReleaseDetection::Reported {
+            stdout().execute(PopKeyboardEnhancementFlags)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Switch shared with the keypad handler, sending key presses as they are while the user
+/// types into a panel of the emulator.
+///
+#[derive(Debug, Clone, Default)]
+pub struct InputCapture(Arc<AtomicBool>);
+
+impl InputCapture {
+    pub fn set(&self, active: bool) {
+        self.0.store(active, Ordering::Relaxed);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+///
+/// Keyboard keys held down, with the action each one triggers. Several keys bound to the same
+/// action hold it down together, the action being released once the last of them is.
 ///
-/// On Chip8, keypad looks like this :
 /// ```
-/// | 1 | 2 | 3 | C |
-/// | 4 | 5 | 6 | D |
-/// | 7 | 8 | 9 | E |
-/// | A | 0 | B | F |
+/// use crossterm::event::KeyCode;
+/// use rusty_chip8::bindings::Action;
+/// use rusty_chip8::keypad::HeldKeys;
+///
+/// let mut held = HeldKeys::default();
+/// assert!(!held.press(KeyCode::Char('w'), Action::Keypad(5), None));
+/// assert!(held.press(KeyCode::Up, Action::Keypad(5), None));
+/// assert_eq!(held.release(KeyCode::Up), None);
+/// assert_eq!(held.release(KeyCode::Char('w')), Some(Action::Keypad(5)));
 /// ```
-/// It's mapped on the left side of the keyboard from keys 1 to 4 (left to right),
-/// through 1 to z (top to bottom)
 ///
-pub(crate) async fn async_listen_keypad_state(keypad_listener: Sender<KeypadEvent>) {
+#[derive(Debug, Clone, Default)]
+pub struct HeldKeys {
+    // Action of each key, and the instant it is released at when releases are not reported.
+    keys: HashMap<KeyCode, (Action, Option<Instant>)>,
+}
+
+impl HeldKeys {
+    ///
+    /// Holds the key down, until `deadline` if given. Returns whether the action was already
+    /// held down, by this key or another one.
+    ///
+    pub fn press(&mut self, code: KeyCode, action: Action, deadline: Option<Instant>) -> bool {
+        let held = self.is_held(action);
+        self.keys.insert(code, (action, deadline));
+        held
+    }
+
+    ///
+    /// Releases the key. Returns its action when no other key holds it down anymore.
+    ///
+    pub fn release(&mut self, code: KeyCode) -> Option<Action> {
+        let (action, _) = self.keys.remove(&code)?;
+        (!self.is_held(action)).then_some(action)
+    }
+
+    ///
+    /// Releases the keys whose deadline passed. Returns the actions not held down anymore.
+    ///
+    pub fn expire(&mut self, now: Instant) -> Vec<Action> {
+        let expired: Vec<KeyCode> = self
+            .keys
+            .iter()
+            .filter(|(_, (_, deadline))| deadline.is_some_and(|deadline| deadline <= now))
+            .map(|(&code, _)| code)
+            .collect();
+        let mut released: Vec<Action> = expired
+            .into_iter()
+            .filter_map(|code| self.release(code))
+            .collect();
+        released.sort();
+        released
+    }
+
+    ///
+    /// Returns the earliest instant a key is to be released at.
+    ///
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.keys
+            .values()
+            .filter_map(|&(_, deadline)| deadline)
+            .min()
+    }
+
+    fn is_held(&self, action: Action) -> bool {
+        self.keys.values().any(|&(held, _)| held == action)
+    }
+}
+
+///
+/// Read terminal key events and forward them as keypad presses and releases,
+/// or emulator actions, according to the given key bindings.
+///
+/// While `capture` is active, key presses are forwarded as [`KeypadEvent::Input`] instead,
+/// releases still going through the bindings so that no key stays held down.
+///
+/// When releases are not reported by the terminal, each key press (including auto-repeats)
+/// pushes back the instant the key is released at, see [`ReleaseDetection::Timeout`].
+/// Hotkeys are only forwarded once per press, ignoring auto-repeats, so that actions such
+/// as rewinding can last as long as their key is held down.
+///
+pub async fn async_listen_keypad_state(
+    keypad_listener: Sender<KeypadEvent>,
+    bindings: KeyBindings,
+    release_detection: ReleaseDetection,
+    capture: InputCapture,
+) {
     let mut event_stream = EventStream::new();
+    let mut held = HeldKeys::default();
     loop {
-        let mut delay = Delay::new(Duration::from_millis(1_000)).fuse();
+        let mut delay = match held.next_deadline() {
+            Some(deadline) => Delay::new(deadline.saturating_duration_since(Instant::now())),
+            None => Delay::new(Duration::from_secs(1)),
+        }
+        .fuse();
 
         let mut event = event_stream.next().fuse();
 
         select! {
-            _ = delay => { keypad_listener.send(Clear).await.unwrap(); },
+            _ = delay => {
+                for action in held.expire(Instant::now()) {
+                    keypad_listener.send(released(action)).await.unwrap();
+                }
+            },
             maybe_event = event => {
-                match maybe_event {
-                    Some(Ok(Key(key))) => {
-                        if key.kind == KeyEventKind::Press {
-                            match key.code {
-                                KeyCode::Char('p') => { keypad_listener.send(Quit).await.unwrap(); },
-
-                                KeyCode::Char('1') => { keypad_listener.send(KeypadEvent::Keypad(0x1u8)).await.unwrap(); },
-                                KeyCode::Char('2') => { keypad_listener.send(KeypadEvent::Keypad(0x2u8)).await.unwrap(); },
-                                KeyCode::Char('3') => { keypad_listener.send(KeypadEvent::Keypad(0x3u8)).await.unwrap(); },
-                                KeyCode::Char('4') => { keypad_listener.send(KeypadEvent::Keypad(0xCu8)).await.unwrap(); },
-
-                                KeyCode::Char('q') => { keypad_listener.send(KeypadEvent::Keypad(0x4u8)).await.unwrap(); },
-                                KeyCode::Char('w') => { keypad_listener.send(KeypadEvent::Keypad(0x5u8)).await.unwrap(); },
-                                KeyCode::Char('e') => { keypad_listener.send(KeypadEvent::Keypad(0x6u8)).await.unwrap(); },
-                                KeyCode::Char('r') => { keypad_listener.send(KeypadEvent::Keypad(0xDu8)).await.unwrap(); },
-
-                                KeyCode::Char('a') => { keypad_listener.send(KeypadEvent::Keypad(0x7u8)).await.unwrap(); },
-                                KeyCode::Char('s') => { keypad_listener.send(KeypadEvent::Keypad(0x8u8)).await.unwrap(); },
-                                KeyCode::Char('d') => { keypad_listener.send(KeypadEvent::Keypad(0x9u8)).await.unwrap(); },
-                                KeyCode::Char('f') => { keypad_listener.send(KeypadEvent::Keypad(0xEu8)).await.unwrap(); },
-
-                                KeyCode::Char('z') => { keypad_listener.send(KeypadEvent::Keypad(0xAu8)).await.unwrap(); },
-                                KeyCode::Char('x') => { keypad_listener.send(KeypadEvent::Keypad(0x0u8)).await.unwrap(); },
-                                KeyCode::Char('c') => { keypad_listener.send(KeypadEvent::Keypad(0xBu8)).await.unwrap(); },
-                                KeyCode::Char('v') => { keypad_listener.send(KeypadEvent::Keypad(0xFu8)).await.unwrap(); },
-                                _ => {},
+                if let Some(Ok(Key(key))) = maybe_event {
+                    let captured = key.kind != KeyEventKind::Release && capture.is_active();
+                    match (key.kind, bindings.action(key.code)) {
+                        _ if captured => {
+                            keypad_listener.send(Input(key.code)).await.unwrap();
+                        }
+                        (KeyEventKind::Press, Some(Action::Quit)) => {
+                            keypad_listener.send(Quit).await.unwrap();
+                        }
+                        (_, None | Some(Action::Quit)) => {}
+                        (KeyEventKind::Release, Some(_)) => {
+                            if let Some(action) = held.release(key.code) {
+                                keypad_listener.send(released(action)).await.unwrap();
+                            }
+                        }
+                        (kind, Some(action)) => {
+                            let deadline = match release_detection {
+                                ReleaseDetection::Timeout(timeout) => {
+                                    Some(Instant::now() + timeout)
+                                }
+                                ReleaseDetection::Reported => None,
+                            };
+                            let repeated = held.press(key.code, action, deadline)
+                                || kind == KeyEventKind::Repeat;
+                            match action {
+                                Action::Keypad(value) => {
+                                    keypad_listener.send(Press(value)).await.unwrap();
+                                }
+                                _ if !repeated => {
+                                    keypad_listener.send(Hotkey(action)).await.unwrap();
+                                }
+                                _ => {}
                             }
                         }
                     }
-                    _ => {},
                 }
             }
         }
     }
 }
 
-pub(crate) fn spawn_keypad_handler(keypad_listener: Sender<KeypadEvent>) -> JoinHandle<()> {
-    async_std::task::spawn(async_listen_keypad_state(keypad_listener))
+fn released(action: Action) -> KeypadEvent {
+    match action {
+        Action::Keypad(value) => Release(value),
+        _ => HotkeyRelease(action),
+    }
 }
 
-pub(crate) enum KeypadEvent {
-    Clear,
-    Keypad(u8),
+///
+/// Spawns [`async_listen_keypad_state`] in the background, reading the terminal events
+/// and forwarding them as [`KeypadEvent`] to the given channel.
+///
+pub fn spawn_keypad_handler(
+    keypad_listener: Sender<KeypadEvent>,
+    bindings: KeyBindings,
+    release_detection: ReleaseDetection,
+    capture: InputCapture,
+) -> JoinHandle<()> {
+    async_std::task::spawn(async_listen_keypad_state(
+        keypad_listener,
+        bindings,
+        release_detection,
+        capture,
+    ))
+}
+
+///
+/// Events produced by the keypad handler, to be consumed by the emulation loop.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeypadEvent {
+    /// The given Chip8 key (0x0 to 0xF) is now held down.
+    Press(u8),
+    /// The given Chip8 key (0x0 to 0xF) is not held down anymore.
+    Release(u8),
+    /// The user asked to leave the emulator.
     Quit,
+    /// The user pressed the hotkey of an emulator action, such as saving the machine.
+    Hotkey(Action),
+    /// The user released the hotkey of an emulator action.
+    HotkeyRelease(Action),
+    /// The user pressed a key while the input is captured, see [`InputCapture`].
+    Input(KeyCode),
+}
+
+impl KeypadEvent {
+    ///
+    /// Applies the event to the keypad state. Returns `false` when the user asked to quit.
+    /// Hotkeys leave the keypad untouched, they are for the emulation loop to handle.
+    ///
+    pub fn apply(self, keypad: &mut Keypad) -> bool {
+        match self {
+            KeypadEvent::Press(key) => keypad.press(key),
+            KeypadEvent::Release(key) => keypad.release(key),
+            KeypadEvent::Quit => return false,
+            KeypadEvent::Hotkey(_) | KeypadEvent::HotkeyRelease(_) | KeypadEvent::Input(_) => {}
+        }
+        true
+    }
+}
+
+///
+/// State of the 16 keys of the Chip8 keypad, one bit per key, key 0x0 being the lowest bit.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub struct Keypad(u16);
+
+impl Keypad {
+    ///
+    /// Creates a keypad from its bitmask, key 0x0 being the lowest bit.
+    ///
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+
+    ///
+    /// Returns the bitmask of the keys held down, key 0x0 being the lowest bit.
+    ///
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    ///
+    /// Marks the given key as held down.
+    ///
+    pub fn press(&mut self, key: u8) {
+        self.0 |= 1 << (key & 0x0F);
+    }
+
+    ///
+    /// Marks the given key as released.
+    ///
+    pub fn release(&mut self, key: u8) {
+        self.0 &= !(1 << (key & 0x0F));
+    }
+
+    ///
+    /// Marks every key as released.
+    ///
+    pub fn release_all(&mut self) {
+        self.0 = 0;
+    }
+
+    ///
+    /// Returns whether the given key is held down. Only the lowest nibble of `key` is used.
+    ///
+    pub const fn is_pressed(self, key: u8) -> bool {
+        self.0 & (1 << (key & 0x0F)) != 0
+    }
+
+    ///
+    /// Returns the lowest key held down, if any.
+    ///
+    pub fn first_pressed(self) -> Option<u8> {
+        (self.0 != 0).then(|| self.0.trailing_zeros() as u8)
+    }
+
+    ///
+    /// Returns the keys held down, in ascending order.
+    ///
+    pub fn pressed(self) -> impl Iterator<Item = u8> {
+        (0..16).filter(move |&key| self.is_pressed(key))
+    }
 }
@@ -0,0 +1,50 @@
+//!
+//! Rusty Chip8, a Chip8 emulator.
+//!
+//! The emulator core lives in [`Chip8`]: load a rom, step it one instruction at a time with
+//! [`Chip8::cycle`] or one 60Hz frame at a time with [`Chip8::run_frame`] while feeding it the
+//! keypad state, and read the screen back with [`Chip8::framebuffer`].
+//!
+//! ```
+//! use rusty_chip8::{Chip8, Keypad};
+//!
+//! let mut vm = Chip8::default();
+//! // 00E0: clear screen, 1200: jump back to the start.
+//! vm.load(&[0x00, 0xE0, 0x12, 0x00]).unwrap();
+//! vm.cycle(Keypad::default()).unwrap();
+//! assert!(vm.framebuffer().iter().all(|&pixel| pixel == 0));
+//! ```
+//!
+
+pub mod asm;
+pub mod audio;
+pub mod bindings;
+pub mod chip8;
+pub mod debugger;
+pub mod disasm;
+pub mod errors;
+pub mod headless;
+pub mod instruction;
+pub mod keypad;
+pub mod memory_view;
+pub mod movie;
+pub mod phosphor;
+pub mod quirks;
+pub mod render;
+pub mod rewind;
+pub mod rng;
+pub mod scheduler;
+pub mod state;
+pub mod theme;
+pub mod variant;
+
+pub use crate::chip8::{
+    Chip8, DEFAULT_STACK_LIMIT, HIRES_SCREEN_HEIGHT, HIRES_SCREEN_WIDTH, MEMORY_SIZE,
+    SCREEN_HEIGHT, SCREEN_WIDTH, XO_CHIP_MEMORY_SIZE,
+};
+pub use crate::errors::EmulationError;
+pub use crate::instruction::Instruction;
+pub use crate::keypad::{Keypad, KeypadEvent};
+pub use crate::quirks::{IndexIncrement, Quirks};
+pub use crate::rng::Rng;
+pub use crate::variant::Variant;
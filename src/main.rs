@@ -1,127 +1,794 @@
-mod chip8;
-mod errors;
-mod keypad;
-
-use std::env;
+use std::error::Error;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use clap::{Parser, Subcommand, ValueEnum};
 
-use crate::chip8::Chip8;
-use ratatui::symbols::Marker;
+use ratatui::text::Line;
 use ratatui::{
     crossterm::{
         terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
         ExecutableCommand,
     },
     prelude::*,
-    widgets::{canvas::*, *},
+    widgets::*,
 };
 
-use crate::errors::EmulationError;
-use crate::keypad::KeypadEvent;
 use async_std::channel::unbounded;
+use rusty_chip8::asm;
+use rusty_chip8::audio::{self, AudioSettings, AudioSink, Bell, Mute, WavRecorder, Waveform};
+use rusty_chip8::bindings::{self, Action, KeyBindings, KeyboardLayout, SAVE_SLOTS};
+use rusty_chip8::debugger::{Debugger, Execution};
+use rusty_chip8::disasm::{self, Syntax};
+use rusty_chip8::headless::{self, InputTimeline};
+use rusty_chip8::keypad::{self, InputCapture, Keypad, KeypadEvent, ReleaseDetection};
+use rusty_chip8::memory_view::{self, MemoryView, Mode, Outcome};
+use rusty_chip8::movie::Movie;
+use rusty_chip8::phosphor::{self, Persistence, Phosphor};
+use rusty_chip8::render::{Palette, Renderer, RendererKind, Screen};
+use rusty_chip8::rewind::{self, History};
+use rusty_chip8::scheduler::{self, Scheduler};
+use rusty_chip8::theme::{ColorDepth, Theme};
+use rusty_chip8::{Chip8, Quirks, Variant};
 use std::{
     io::{self, stdout, Stdout},
+    thread,
     time::{Duration, Instant},
 };
 
-fn main() -> io::Result<()> {
-    let args: Vec<String> = env::args().collect();
-    println!("Loading {:?}", args);
-    let rom_path = &args[1];
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about = "A Chip8 emulator running in the terminal",
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true
+)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    let (keypad_listener, vm_receiver) = unbounded();
+    /// Path to the rom to run.
+    #[arg(required = true)]
+    rom: Option<PathBuf>,
 
-    let contents = fs::read(rom_path).expect("Cannot read file");
+    /// Chip8 variant the rom was written for: chip8, schip or xochip.
+    #[arg(long, default_value = "chip8")]
+    variant: Variant,
 
-    let mut terminal = init_terminal()?;
+    /// Quirks profile matching the interpreter the rom was written for:
+    /// vip, chip48, schip or xochip.
+    /// Defaults to the usual profile of the selected variant.
+    #[arg(long)]
+    quirks: Option<Quirks>,
+
+    /// Instructions executed per 60Hz frame.
+    #[arg(long, default_value_t = scheduler::DEFAULT_INSTRUCTIONS_PER_FRAME, conflicts_with = "hz")]
+    ipf: usize,
+
+    /// Clock speed in instructions per second, an alternative to --ipf.
+    #[arg(long)]
+    hz: Option<u32>,
+
+    /// Seed of the random number generator, to reproduce a run. Random when omitted.
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Delay in milliseconds after which a key is released, for terminals
+    /// that do not report key releases.
+    #[arg(long, default_value_t = keypad::DEFAULT_RELEASE_TIMEOUT.as_millis() as u64)]
+    release_timeout: u64,
+
+    /// Always release keys after --release-timeout, even when the terminal reports releases.
+    #[arg(long)]
+    no_release_events: bool,
+
+    /// Keyboard layout of the built-in key bindings: qwerty, azerty, qwertz or dvorak.
+    #[arg(long, default_value = "qwerty")]
+    layout: KeyboardLayout,
+
+    /// TOML file customizing the key bindings, on top of the --layout preset.
+    #[arg(long)]
+    bindings: Option<PathBuf>,
+
+    /// Frames kept to rewind the game, 0 disabling rewinding.
+    #[arg(long, default_value_t = rewind::DEFAULT_REWIND_FRAMES)]
+    rewind_frames: usize,
+
+    /// Records the session into a movie file, written when leaving the emulator.
+    #[arg(long, value_name = "MOVIE")]
+    record: Option<PathBuf>,
+
+    /// Replays a movie recorded with --record, reporting the frame it desynchronizes at.
+    /// The variant, quirks, speed and seed of the recording are used.
+    #[arg(long, value_name = "MOVIE", conflicts_with = "record")]
+    replay: Option<PathBuf>,
+
+    /// Pauses the machine in the debugger when the program counter reaches the given
+    /// address, such as 0x2A0. Can be repeated.
+    #[arg(long = "break", value_name = "ADDRESS", value_parser = parse_address,
+          conflicts_with_all = ["record", "replay"])]
+    breakpoints: Vec<u16>,
+
+    /// Runs without a terminal for --frames frames, then writes the requested screen dumps.
+    /// Exits with a non-zero code when the emulation fails.
+    #[arg(long, conflicts_with_all = ["record", "replay", "breakpoints"])]
+    headless: bool,
+
+    /// Frames run in headless mode.
+    #[arg(long, default_value_t = 600, requires = "headless")]
+    frames: usize,
+
+    /// Ends the headless run as soon as the program counter reaches the given address.
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_address, requires = "headless")]
+    until_pc: Option<u16>,
 
-    let join = keypad::spawn_keypad_handler(keypad_listener);
+    /// Keys held down during the headless run: one `FRAME KEYS` line per change, such as
+    /// `60 5` to hold key 5 from frame 60 on, or `90 -` to release every key.
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    input: Option<PathBuf>,
 
-    // pooling time.
-    let mut last_tick = Instant::now();
+    /// Writes the screen at the end of the headless run as a PBM or PNG image,
+    /// depending on the extension.
+    #[arg(long, value_name = "IMAGE", requires = "headless")]
+    screenshot: Option<PathBuf>,
 
-    // 60hz
-    let tick_rate = Duration::from_millis(16);
+    /// Writes the screen at the end of the headless run as text, `-` for the standard output.
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    ascii: Option<PathBuf>,
 
-    let mut vm = Chip8::default();
+    /// Records the sound of the headless run into a WAV file.
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    wav: Option<PathBuf>,
 
-    vm.load(contents);
-    let mut keypad_value: Option<u8> = None;
+    /// Where the buzzer sounds: the sound device, which needs the `audio` feature,
+    /// the terminal bell, or nowhere.
+    #[arg(long, value_enum, default_value_t = AudioOutput::default())]
+    audio: AudioOutput,
 
-    loop {
-        // perform one cycle
-        match vm_receiver.try_recv() {
-            Ok(KeypadEvent::Keypad(value)) => keypad_value = Some(value),
-            Ok(KeypadEvent::Quit) => break,
-            Ok(KeypadEvent::Clear) => keypad_value = None,
-            _ => {}
-        };
+    /// Frequency of the buzzer tone in Hz.
+    #[arg(long, default_value_t = audio::DEFAULT_FREQUENCY)]
+    tone_frequency: f32,
 
-        if last_tick.elapsed() >= tick_rate {
-            if let Err(error) = vm.cycle(keypad_value) {
-                match error {
-                    EmulationError::UnknownOpcode(opcode) => {
-                        panic!("something wrong happened, {:?}", opcode)
+    /// Volume of the buzzer tone, from 0 to 1.
+    #[arg(long, default_value_t = audio::DEFAULT_VOLUME)]
+    volume: f32,
+
+    /// Shape of the buzzer tone: square, triangle, sawtooth or sine.
+    #[arg(long, default_value = "square")]
+    waveform: Waveform,
+
+    /// How the screen is drawn: half-block for square pixels, braille for tiny terminals,
+    /// or canvas to stretch it to the window.
+    #[arg(long, default_value = "half-block")]
+    renderer: RendererKind,
+
+    /// Keeps cleared pixels visible for a while, hiding the flicker of moving sprites:
+    /// off, decay to fade them out over --decay-frames frames, or blend to combine the last
+    /// two frames.
+    #[arg(long, default_value = "off")]
+    persistence: Persistence,
+
+    /// Frames cleared pixels take to fade out with --persistence decay.
+    #[arg(long, default_value_t = phosphor::DEFAULT_DECAY_FRAMES)]
+    decay_frames: u8,
+
+    /// Colours of the screen: classic, green, amber, lcd or high-contrast.
+    #[arg(long, default_value = "classic")]
+    theme: Theme,
+
+    /// Colour of the lit pixels, replacing the one of the theme for plane 1,
+    /// as a name such as `green` or as `#RRGGBB`.
+    #[arg(long, value_name = "COLOR")]
+    foreground: Option<Color>,
+
+    /// Colour of the unlit pixels, replacing the one of the theme.
+    #[arg(long, value_name = "COLOR")]
+    background: Option<Color>,
+
+    /// Colours the terminal displays: truecolor, 256 or 16, the colours of the theme being
+    /// replaced by the closest ones. Guessed from the COLORTERM and TERM variables when omitted.
+    #[arg(long, value_name = "DEPTH")]
+    colors: Option<ColorDepth>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum AudioOutput {
+    Device,
+    Bell,
+    Off,
+}
+
+impl Default for AudioOutput {
+    fn default() -> Self {
+        if cfg!(feature = "audio") {
+            AudioOutput::Device
+        } else {
+            AudioOutput::Bell
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Assembles a source written in a subset of the Octo syntax into a rom.
+    Asm {
+        /// Path to the source to assemble.
+        source: PathBuf,
+
+        /// Path of the rom to write, the source path with a .ch8 extension when omitted.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Prints the disassembly of a rom, telling code from data by following its jumps.
+    Disasm {
+        /// Path to the rom to disassemble.
+        rom: PathBuf,
+
+        /// Syntax of the instructions: classic or octo.
+        #[arg(long, default_value = "classic")]
+        syntax: Syntax,
+    },
+}
+
+fn parse_address(address: &str) -> Result<u16, String> {
+    let digits = address.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(digits, 16).map_err(|error| format!("invalid address: {}", error))
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    match &args.command {
+        Some(Command::Asm { source, output }) => {
+            let rom = match asm::assemble(&fs::read_to_string(source)?) {
+                Ok(rom) => rom,
+                Err(error) => {
+                    eprintln!("{}: {}", source.display(), error);
+                    process::exit(1);
+                }
+            };
+            let output = output
+                .clone()
+                .unwrap_or_else(|| source.with_extension("ch8"));
+            fs::write(&output, &rom)?;
+            println!("Assembled {} bytes into {:?}", rom.len(), output);
+            return Ok(());
+        }
+        Some(Command::Disasm { rom, syntax }) => {
+            let entries = disasm::disassemble(&fs::read(rom)?, *syntax);
+            print!("{}", disasm::listing(&entries, *syntax));
+            return Ok(());
+        }
+        None => {}
+    }
+    let rom = args.rom.clone().ok_or("missing rom")?;
+
+    let (keypad_listener, vm_receiver) = unbounded();
+
+    let contents = fs::read(&rom)?;
+
+    let bindings = match &args.bindings {
+        Some(path) => KeyBindings::from_toml(&fs::read_to_string(path)?, args.layout)?,
+        None => KeyBindings::preset(args.layout),
+    };
+    let slots = |action: fn(u8) -> Action| {
+        (1..=SAVE_SLOTS)
+            .map(|slot| key_names(&bindings, action(slot)))
+            .collect::<Vec<_>>()
+            .join("', '")
+    };
+    let help = format!(
+        "Press '{}' to quit, hold '{}' to rewind, '{}' for the debugger, '{}' for the memory.\n\
+         Save states into slots 1 to {}: '{}', load them: '{}'.",
+        key_names(&bindings, Action::Quit),
+        key_names(&bindings, Action::Rewind),
+        key_names(&bindings, Action::Debugger),
+        key_names(&bindings, Action::Memory),
+        SAVE_SLOTS,
+        slots(Action::SaveState),
+        slots(Action::LoadState)
+    );
+    let debugger_help = format!(
+        "'{}' pause/continue, '{}' step, '{}' step over, '{}' step out, '{}' breakpoint",
+        key_names(&bindings, Action::Pause),
+        key_names(&bindings, Action::Step),
+        key_names(&bindings, Action::StepOver),
+        key_names(&bindings, Action::StepOut),
+        key_names(&bindings, Action::Breakpoint)
+    );
+
+    let replay = match &args.replay {
+        Some(path) => Some(Movie::from_bytes(&fs::read(path)?)?),
+        None => None,
+    };
+    let (mut vm, seed, instructions_per_frame) = match &replay {
+        Some(movie) => (
+            movie.machine(&contents)?,
+            movie.seed,
+            movie.instructions_per_frame,
+        ),
+        None => {
+            let quirks = args.quirks.unwrap_or_else(|| args.variant.default_quirks());
+            let mut vm = Chip8::with_variant(args.variant, quirks);
+            let seed = args.seed.unwrap_or_else(rand::random);
+            vm.seed(seed);
+            vm.load(&contents)?;
+            let instructions_per_frame =
+                args.hz.map_or(args.ipf, scheduler::instructions_per_frame);
+            (vm, seed, instructions_per_frame)
+        }
+    };
+    if args.headless {
+        return run_headless(&args, vm, instructions_per_frame);
+    }
+    println!("Loading {:?}", args);
+    vm.history = History::new(args.rewind_frames);
+    let mut recording = args
+        .record
+        .as_ref()
+        .map(|_| Movie::new(&vm, seed, instructions_per_frame));
+    // Frames played back so far, when replaying a movie.
+    let mut replayed = 0;
+
+    let mut terminal = init_terminal()?;
+
+    let release_timeout = Duration::from_millis(args.release_timeout);
+    let release_detection = if args.no_release_events {
+        ReleaseDetection::Timeout(release_timeout)
+    } else {
+        ReleaseDetection::enable(release_timeout)
+    };
+    // Keys typed into the memory panel that it has no use for still trigger their actions.
+    let panel_bindings = bindings.clone();
+    let capture = InputCapture::default();
+    let join = keypad::spawn_keypad_handler(
+        keypad_listener,
+        bindings,
+        release_detection,
+        capture.clone(),
+    );
+
+    let mut scheduler = Scheduler::default();
+    let mut keypad_value = Keypad::default();
+    let mut failure: Option<Box<dyn Error>> = None;
+    let (mut audio, mut status) = open_audio(&args);
+    let renderer = args.renderer.renderer();
+    let mut palette = args.theme.palette();
+    if let Some(color) = args.foreground {
+        palette.planes[0] = color;
+    }
+    if let Some(color) = args.background {
+        palette.background = color;
+    }
+    let color_depth = args.colors.unwrap_or_else(ColorDepth::detect);
+    let mut phosphor = Phosphor::new(args.persistence, args.decay_frames);
+    let mut rewinding = false;
+    let mut debugger = Debugger::default();
+    debugger.breakpoints.extend(&args.breakpoints);
+    let mut show_debugger = false;
+    let mut memory_view = MemoryView::default();
+    let mut show_memory = false;
+    // Whether keys go to the memory panel rather than to the machine.
+    let mut memory_focused = false;
+
+    'emulation: loop {
+        while let Ok(event) = vm_receiver.try_recv() {
+            let event = match event {
+                KeypadEvent::Input(code) => {
+                    let editable = debugger.is_paused() && recording.is_none() && replay.is_none();
+                    match memory_view.input(code, &mut vm, editable) {
+                        Outcome::Handled => continue,
+                        Outcome::ReadOnly if recording.is_some() || replay.is_some() => {
+                            status =
+                                "Memory editing is disabled while recording or replaying.".into();
+                            continue;
+                        }
+                        Outcome::ReadOnly => {
+                            status = "Pause the machine before editing memory.".into();
+                            continue;
+                        }
+                        Outcome::Leave => {
+                            memory_focused = false;
+                            continue;
+                        }
+                        // Rewinding lasts until a release, which only comes to unfocused panels.
+                        Outcome::Ignored => match panel_bindings.action(code) {
+                            Some(Action::Quit) => KeypadEvent::Quit,
+                            None | Some(Action::Keypad(_) | Action::Rewind) => continue,
+                            Some(action) => KeypadEvent::Hotkey(action),
+                        },
+                    }
+                }
+                event => event,
+            };
+            match event {
+                KeypadEvent::Hotkey(Action::SaveState(slot)) => {
+                    status = save_slot(&vm, &rom, slot);
+                }
+                KeypadEvent::Hotkey(Action::LoadState(_) | Action::Rewind) if replay.is_some() => {
+                    status = "Loading states and rewinding are disabled while replaying.".into();
+                }
+                KeypadEvent::Hotkey(Action::LoadState(_)) if recording.is_some() => {
+                    status = "Loading states is disabled while recording.".into();
+                }
+                KeypadEvent::Hotkey(Action::LoadState(slot)) => {
+                    status = load_slot(&mut vm, &rom, slot);
+                }
+                KeypadEvent::Hotkey(Action::Rewind) => rewinding = true,
+                KeypadEvent::Hotkey(Action::Debugger) => show_debugger = !show_debugger,
+                KeypadEvent::Hotkey(Action::Memory) => {
+                    show_memory = !(show_memory && memory_focused);
+                    memory_focused = show_memory;
+                }
+                KeypadEvent::Hotkey(
+                    Action::Pause
+                    | Action::Step
+                    | Action::StepOver
+                    | Action::StepOut
+                    | Action::Breakpoint,
+                ) if recording.is_some() || replay.is_some() => {
+                    status = "The debugger is disabled while recording or replaying.".into();
+                }
+                KeypadEvent::Hotkey(Action::Pause) if debugger.is_paused() => debugger.resume(),
+                KeypadEvent::Hotkey(Action::Pause) => debugger.pause(),
+                KeypadEvent::Hotkey(Action::Breakpoint) => {
+                    let address = vm.program_counter;
+                    status = if debugger.toggle_breakpoint(address) {
+                        format!("Breakpoint set at {:#06X}.", address)
+                    } else {
+                        format!("Breakpoint removed at {:#06X}.", address)
+                    };
+                }
+                KeypadEvent::Hotkey(Action::Step | Action::StepOver | Action::StepOut)
+                    if !debugger.is_paused() =>
+                {
+                    status = "Pause the machine before stepping.".into();
+                }
+                KeypadEvent::Hotkey(action @ (Action::Step | Action::StepOver)) => {
+                    let stepped = if action == Action::Step {
+                        debugger.step(&mut vm, keypad_value)
+                    } else {
+                        debugger.step_over(&mut vm, keypad_value)
+                    };
+                    if let Err(error) = stepped {
+                        status = format!("Cannot step: {}.", error);
                     }
                 }
+                KeypadEvent::Hotkey(Action::StepOut) => debugger.step_out(&vm),
+                KeypadEvent::HotkeyRelease(Action::Rewind) => {
+                    rewinding = false;
+                    status.clear();
+                }
+                event if !event.apply(&mut keypad_value) => break 'emulation,
+                _ => {}
             }
+        }
+        capture.set(show_memory && memory_focused);
+
+        let frames = scheduler.frames_due(Instant::now());
+        for _ in 0..frames {
+            if rewinding {
+                let rewound = vm.rewind(1);
+                if let Some(movie) = &mut recording {
+                    movie
+                        .frames
+                        .truncate(movie.frames.len().saturating_sub(rewound));
+                }
+                status = format!("Rewinding, {} frames left.", vm.history.len());
+                audio.pause();
+                continue;
+            }
+            if let Some(movie) = &replay {
+                if replayed == movie.frames.len() {
+                    break 'emulation;
+                }
+                if let Err(error) = movie.replay_frame(replayed, &mut vm) {
+                    failure = Some(error.into());
+                    break 'emulation;
+                }
+                replayed += 1;
+                audio.frame(&vm);
+                status = format!("Replaying frame {} of {}.", replayed, movie.frames.len());
+                continue;
+            }
+            match debugger.run_frame(&mut vm, keypad_value, instructions_per_frame) {
+                Ok(true) => audio.frame(&vm),
+                Ok(false) => {
+                    audio.pause();
+                    break;
+                }
+                Err(error) => {
+                    failure = Some(error.into());
+                    break 'emulation;
+                }
+            }
+            if debugger.is_paused() {
+                show_debugger = true;
+                status = format!("Paused at {:#06X}.", vm.program_counter);
+            }
+            if let Some(movie) = &mut recording {
+                movie.record(keypad_value, &vm);
+            }
+        }
+        if frames > 0 {
+            // The screen persistence follows the refreshes, running or not, so the screen
+            // settles on the state of a paused machine.
+            phosphor.update(&vm);
+            memory_view.refresh(&vm.memory);
             let _ = terminal.draw(|frame| {
                 let [top, bottom] =
                     Layout::vertical([Constraint::Percentage(70), Constraint::Fill(1)])
                         .areas(frame.area());
-                let [top_left, top_right] =
-                    Layout::horizontal([Constraint::Percentage(35), Constraint::Fill(1)])
-                        .areas(top);
-                frame.render_widget(as_canvas(&vm), top_left);
-                frame.render_widget(as_debug(&vm, keypad_value), top_right);
-                frame.render_widget(as_instruction(), bottom);
+                let size = renderer.size(vm.width(), vm.height());
+                let [top_left, top_right] = Layout::horizontal([
+                    size.map_or(Constraint::Percentage(35), |(columns, _)| {
+                        Constraint::Length(columns + 2)
+                    }),
+                    Constraint::Fill(1),
+                ])
+                .areas(top);
+                let screen = phosphor.screen();
+                let colors = (&palette, color_depth);
+                render_screen(screen, renderer.as_ref(), colors, size, top_left, frame);
+                let top_right = match (show_memory, show_debugger) {
+                    (false, _) => top_right,
+                    (true, false) => {
+                        render_memory(&vm, &mut memory_view, memory_focused, top_right, frame);
+                        Rect::default()
+                    }
+                    (true, true) => {
+                        let [debugger, memory] =
+                            Layout::vertical([Constraint::Percentage(50), Constraint::Fill(1)])
+                                .areas(top_right);
+                        render_memory(&vm, &mut memory_view, memory_focused, memory, frame);
+                        debugger
+                    }
+                };
+                if show_debugger {
+                    let [disassembly, machine] =
+                        Layout::horizontal([Constraint::Percentage(50), Constraint::Fill(1)])
+                            .areas(top_right);
+                    let rows = disassembly.height.saturating_sub(2) as usize;
+                    frame.render_widget(as_disassembly(&vm, &debugger, rows), disassembly);
+                    frame.render_widget(as_machine(&vm, &debugger, &debugger_help), machine);
+                } else if !show_memory {
+                    frame.render_widget(as_debug(&vm, keypad_value), top_right);
+                }
+                frame.render_widget(as_instruction(&help, release_detection, &status), bottom);
             });
-            last_tick = Instant::now();
         }
+        thread::sleep(scheduler.until_next_frame(Instant::now()));
     }
+    // Stops the sound device before leaving.
+    drop(audio);
     async_std::task::block_on(join.cancel());
-    restore_terminal()
+    release_detection.disable()?;
+    restore_terminal()?;
+    println!("Run seed: {} (replay with --seed {})", seed, seed);
+    if let (Some(movie), Some(path)) = (&recording, &args.record) {
+        fs::write(path, movie.to_bytes())?;
+        println!("Recorded {} frames into {:?}", movie.frames.len(), path);
+    }
+    if let Some(movie) = &replay {
+        if failure.is_none() {
+            println!(
+                "Replayed {} of {} frames without desynchronization",
+                replayed,
+                movie.frames.len()
+            );
+        }
+    }
+    if let Some(error) = failure {
+        eprintln!("Emulation stopped: {}", error);
+        process::exit(1);
+    }
+    Ok(())
 }
 
-///
-/// Returns points in the canvas screen referential.
-/// Chip8 have a top left coordinates being (0,0),
-/// while ratatui works with the bottom left coordinates being (0,0)
-///
-fn as_points(vm: &Chip8) -> Vec<(f64, f64)> {
-    let mut y_axis = 32;
-    let mut x_axis = 0;
-    let mut coords = vec![];
-    for pixel in vm.gfx {
-        if pixel {
-            coords.push((x_axis as f64, y_axis as f64))
+fn run_headless(
+    args: &Args,
+    mut vm: Chip8,
+    instructions_per_frame: usize,
+) -> Result<(), Box<dyn Error>> {
+    let timeline = match &args.input {
+        Some(path) => fs::read_to_string(path)?.parse()?,
+        None => InputTimeline::default(),
+    };
+    let mut recorder = WavRecorder::new(audio_settings(args));
+    let run = headless::run(
+        &mut vm,
+        &timeline,
+        args.frames,
+        instructions_per_frame,
+        args.until_pc,
+        &mut recorder,
+    );
+    if let Some(path) = &args.wav {
+        fs::write(path, recorder.to_wav())?;
+    }
+    if let Some(path) = &args.screenshot {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("pbm") => fs::write(path, headless::pbm(&vm))?,
+            Some("png") => headless::write_png(&vm, fs::File::create(path)?)?,
+            _ => return Err(format!("unsupported screenshot format {:?}", path).into()),
+        }
+    }
+    match &args.ascii {
+        Some(path) if path.as_os_str() == "-" => print!("{}", headless::ascii(&vm)),
+        Some(path) => fs::write(path, headless::ascii(&vm))?,
+        None => {}
+    }
+    match run {
+        Ok(frames) => {
+            eprintln!(
+                "Ran {} frames, program counter at {:#06X}",
+                frames, vm.program_counter
+            );
+            Ok(())
         }
-        x_axis += 1;
-        if x_axis % 64 == 0 {
-            y_axis -= 1;
-            x_axis = 0;
+        Err(error) => {
+            eprintln!("Emulation stopped: {}", error);
+            process::exit(1);
         }
     }
-    coords
 }
 
-fn as_canvas(vm: &Chip8) -> impl Widget {
-    let coords = as_points(vm);
-    Canvas::default()
-        .block(Block::bordered().title("Screen"))
-        .marker(Marker::Block)
-        .x_bounds([0.0, 64.0])
-        .y_bounds([0.0, 32.0])
-        .paint(move |ctx| {
-            ctx.draw(&Points {
-                coords: &coords,
-                color: Color::default(),
-            });
+fn audio_settings(args: &Args) -> AudioSettings {
+    AudioSettings {
+        frequency: args.tone_frequency,
+        volume: args.volume,
+        waveform: args.waveform,
+    }
+}
+
+///
+/// Opens the sink selected by --audio, falling back to the terminal bell when the sound device
+/// is not available, along with a status message explaining why.
+///
+fn open_audio(args: &Args) -> (Box<dyn AudioSink>, String) {
+    let bell = || Box::new(Bell::new(stdout())) as Box<dyn AudioSink>;
+    match args.audio {
+        AudioOutput::Off => (Box::new(Mute), String::new()),
+        AudioOutput::Bell => (bell(), String::new()),
+        #[cfg(feature = "audio")]
+        AudioOutput::Device => match audio::Device::open(audio_settings(args)) {
+            Ok(device) => (Box::new(device), String::new()),
+            Err(error) => (bell(), format!("{}, using the terminal bell.", error)),
+        },
+        #[cfg(not(feature = "audio"))]
+        AudioOutput::Device => (
+            bell(),
+            "Built without the audio feature, using the terminal bell.".into(),
+        ),
+    }
+}
+
+fn key_names(bindings: &KeyBindings, action: Action) -> String {
+    bindings
+        .keys(action)
+        .iter()
+        .map(|&code| bindings::key_name(code))
+        .collect::<Vec<_>>()
+        .join("' or '")
+}
+
+///
+/// Save states are stored next to the rom, `game.ch8` using `game.state1` to `game.state4`.
+///
+fn slot_path(rom: &Path, slot: u8) -> PathBuf {
+    rom.with_extension(format!("state{}", slot))
+}
+
+fn save_slot(vm: &Chip8, rom: &Path, slot: u8) -> String {
+    match fs::write(slot_path(rom, slot), vm.save_state()) {
+        Ok(()) => format!("State saved into slot {}.", slot),
+        Err(error) => format!("Cannot save slot {}: {}.", slot, error),
+    }
+}
+
+fn load_slot(vm: &mut Chip8, rom: &Path, slot: u8) -> String {
+    let loaded = fs::read(slot_path(rom, slot))
+        .map_err(|error| error.to_string())
+        .and_then(|state| vm.load_state(&state).map_err(|error| error.to_string()));
+    match loaded {
+        Ok(()) => format!("State loaded from slot {}.", slot),
+        Err(error) => format!("Cannot load slot {}: {}.", slot, error),
+    }
+}
+
+///
+/// Draws the screen in a bordered block, sized to the screen when the renderer has a fixed size.
+///
+fn render_screen(
+    screen: Screen<'_>,
+    renderer: &dyn Renderer,
+    (palette, color_depth): (&Palette, ColorDepth),
+    size: Option<(u16, u16)>,
+    area: Rect,
+    frame: &mut Frame,
+) {
+    let area = match size {
+        Some((_, rows)) => Rect {
+            height: area.height.min(rows + 2),
+            ..area
+        },
+        None => area,
+    };
+    let block = Block::bordered().title("Screen");
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    renderer.render(screen, palette, inner, frame.buffer_mut());
+    color_depth.convert_area(frame.buffer_mut(), inner);
+}
+
+///
+/// Hex and ASCII dump of the memory, scrolled to the cursor, with the bytes at the program
+/// counter and the index register highlighted, and the recently written ones flashing.
+///
+fn render_memory(vm: &Chip8, view: &mut MemoryView, focused: bool, area: Rect, frame: &mut Frame) {
+    let block = Block::bordered().title("Memory (PC blue, I magenta)");
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    // Address, bytes then their characters, with a line left for the prompt or the help.
+    let bytes_per_row = if inner.width >= 4 + 16 * 3 + 2 + 16 {
+        16
+    } else {
+        8
+    };
+    let rows = inner.height.saturating_sub(1) as usize;
+    view.scroll_into_view(rows, bytes_per_row);
+    let pc = vm.program_counter as usize;
+    let index = vm.memory_index as usize;
+    let mut lines: Vec<Line> = (view.top..vm.memory.len())
+        .step_by(bytes_per_row)
+        .take(rows)
+        .map(|start| {
+            let end = (start + bytes_per_row).min(vm.memory.len());
+            let mut spans = vec![Span::raw(format!("{:04X} ", start))];
+            for address in start..end {
+                let mut style = Style::new();
+                if address == pc || address == pc + 1 {
+                    style = style.bg(Color::Blue);
+                } else if address == index {
+                    style = style.bg(Color::Magenta);
+                }
+                if view.is_flashing(address) {
+                    style = style.fg(Color::Yellow).bold();
+                }
+                let text = match view.pending {
+                    Some(high) if focused && address == view.cursor => format!("{:X}_", high),
+                    _ => format!("{:02X}", vm.memory[address]),
+                };
+                if focused && address == view.cursor {
+                    style = style.reversed();
+                }
+                spans.push(Span::raw(" "));
+                spans.push(Span::styled(text, style));
+            }
+            let ascii: String = vm.memory[start..end]
+                .iter()
+                .map(|&byte| memory_view::ascii(byte))
+                .collect();
+            spans.push(Span::raw(format!("  {}", ascii)));
+            Line::from(spans)
         })
+        .collect();
+    lines.push(Line::raw(match &view.mode {
+        Mode::GoTo(address) => format!("Go to: {}_", address),
+        Mode::Browse if focused => {
+            "Arrows move, 'g' go to, hex digits edit when paused, 'Esc' leave.".into()
+        }
+        Mode::Browse => String::new(),
+    }));
+    frame.render_widget(Paragraph::new(lines), inner);
 }
 
-fn as_debug(vm: &Chip8, keypad: Option<u8>) -> impl Widget {
-    let mut content = format!("{:?}", keypad);
+fn as_debug(vm: &Chip8, keypad: Keypad) -> impl Widget {
+    let mut content = String::from("Keys:");
+    keypad
+        .pressed()
+        .for_each(|key| content.push_str(&format!(" {:X}", key)));
+    content.push('\n');
     #[cfg(debug_assertions)]
     vm.debug_log.iter().for_each(|line| {
         content.push_str(line);
@@ -130,8 +797,82 @@ fn as_debug(vm: &Chip8, keypad: Option<u8>) -> impl Widget {
     Paragraph::new(content).block(Block::bordered().title("Debug Logs"))
 }
 
-fn as_instruction() -> impl Widget {
-    Paragraph::new("Press 'p' to quit.").block(Block::bordered().title("Instructions"))
+///
+/// Disassembly of `rows` words around the program counter, which is highlighted.
+///
+fn as_disassembly(vm: &Chip8, debugger: &Debugger, rows: usize) -> impl Widget {
+    let pc = vm.program_counter as usize;
+    let start = pc.saturating_sub(rows / 2 * 2);
+    let lines: Vec<Line> = (start..vm.memory.len().saturating_sub(1))
+        .step_by(2)
+        .take(rows)
+        .map(|address| {
+            let opcode = (vm.memory[address] as u16) << 8 | vm.memory[address + 1] as u16;
+            let breakpoint = if debugger.breakpoints.contains(&(address as u16)) {
+                '●'
+            } else {
+                ' '
+            };
+            let text = format!(
+                "{}{:04X}  {:04X}  {}",
+                breakpoint,
+                address,
+                opcode,
+                disasm::mnemonic(opcode, Syntax::Classic).unwrap_or_default()
+            );
+            if address == pc {
+                Line::styled(text, Style::new().reversed())
+            } else {
+                Line::raw(text)
+            }
+        })
+        .collect();
+    Paragraph::new(lines).block(Block::bordered().title("Disassembly"))
+}
+
+///
+/// Registers, timers, call stack and execution state of the machine.
+///
+fn as_machine(vm: &Chip8, debugger: &Debugger, debugger_help: &str) -> impl Widget {
+    let mut content = String::new();
+    for (row, registers) in vm.register.chunks(4).enumerate() {
+        for (column, value) in registers.iter().enumerate() {
+            content.push_str(&format!("V{:X} {:02X}  ", row * 4 + column, value));
+        }
+        content.push('\n');
+    }
+    content.push_str(&format!(
+        "PC {:04X}  I {:04X}\nDT {:02X}  ST {:02X}\n",
+        vm.program_counter, vm.memory_index, vm.delay_timer, vm.sound_timer
+    ));
+    let execution = match debugger.execution() {
+        Execution::Running => "running",
+        Execution::Paused => "paused",
+        Execution::StepOver { .. } => "stepping over",
+        Execution::StepOut { .. } => "stepping out",
+    };
+    content.push_str(&format!("State: {}\nStack:", execution));
+    vm.stack
+        .iter()
+        .rev()
+        .for_each(|address| content.push_str(&format!(" {:04X}", address)));
+    content.push_str("\nBreakpoints:");
+    debugger
+        .breakpoints
+        .iter()
+        .for_each(|address| content.push_str(&format!(" {:04X}", address)));
+    content.push_str(&format!("\n\n{}.", debugger_help));
+    Paragraph::new(content)
+        .wrap(Wrap { trim: false })
+        .block(Block::bordered().title("Machine"))
+}
+
+fn as_instruction(help: &str, release_detection: ReleaseDetection, status: &str) -> impl Widget {
+    Paragraph::new(format!(
+        "{}\nInput: {}.\n{}",
+        help, release_detection, status
+    ))
+    .block(Block::bordered().title("Instructions"))
 }
 
 fn init_terminal() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
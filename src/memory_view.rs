@@ -0,0 +1,210 @@
+use crate::chip8::Chip8;
+use crossterm::event::KeyCode;
+
+///
+/// Screen refreshes a written byte stays highlighted for, half a second at 60 frames per second.
+///
+pub const FLASH_FRAMES: u8 = 30;
+
+///
+/// What the keys typed into the memory panel do.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Keys move the cursor, hexadecimal digits overwrite the byte under it.
+    #[default]
+    Browse,
+    /// Hexadecimal digits of the address typed after `g`, the cursor moving there on `Enter`.
+    GoTo(String),
+}
+
+///
+/// What became of a key handed to [`MemoryView::input`].
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The key moved the cursor, edited memory or went to the prompt.
+    Handled,
+    /// The key is not for the memory panel.
+    Ignored,
+    /// The key would have edited memory, which is not allowed at the moment.
+    ReadOnly,
+    /// The user is done with the panel.
+    Leave,
+}
+
+///
+/// State of the hex memory panel: a cursor moving over the address space, rows of bytes
+/// scrolled to keep it in sight, and the bytes written recently.
+///
+/// ```
+/// use crossterm::event::KeyCode;
+/// use rusty_chip8::memory_view::{MemoryView, Outcome};
+/// use rusty_chip8::Chip8;
+///
+/// let mut vm = Chip8::default();
+/// let mut view = MemoryView::default();
+/// for key in ['g', '3', '0', '0'] {
+///     view.input(KeyCode::Char(key), &mut vm, true);
+/// }
+/// view.input(KeyCode::Enter, &mut vm, true);
+/// assert_eq!(view.cursor, 0x300);
+/// assert_eq!(view.input(KeyCode::Char('a'), &mut vm, true), Outcome::Handled);
+/// assert_eq!(view.input(KeyCode::Char('5'), &mut vm, true), Outcome::Handled);
+/// assert_eq!((vm.memory[0x300], view.cursor), (0xA5, 0x301));
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct MemoryView {
+    /// Address of the byte under the cursor.
+    pub cursor: usize,
+    /// Address of the first row shown.
+    pub top: usize,
+    pub bytes_per_row: usize,
+    /// Rows shown, paging moving the cursor by as many rows.
+    pub rows: usize,
+    pub mode: Mode,
+    /// High nibble typed over the byte under the cursor, waiting for the low one.
+    pub pending: Option<u8>,
+    // Memory as of the last refresh, to spot the bytes written since.
+    previous: Vec<u8>,
+    // Refreshes left before each written byte stops being highlighted.
+    flashes: Vec<u8>,
+}
+
+impl Default for MemoryView {
+    fn default() -> Self {
+        Self {
+            cursor: 0x200,
+            top: 0x200,
+            bytes_per_row: 16,
+            rows: 16,
+            mode: Mode::Browse,
+            pending: None,
+            previous: Vec::new(),
+            flashes: Vec::new(),
+        }
+    }
+}
+
+impl MemoryView {
+    ///
+    /// Spots the bytes written since the last refresh, to be called once per screen refresh.
+    ///
+    pub fn refresh(&mut self, memory: &[u8]) {
+        if self.previous.len() != memory.len() {
+            self.previous = memory.to_vec();
+            self.flashes = vec![0; memory.len()];
+            self.cursor = self.cursor.min(memory.len() - 1);
+            return;
+        }
+        for ((previous, &byte), flash) in
+            self.previous.iter_mut().zip(memory).zip(&mut self.flashes)
+        {
+            if *previous != byte {
+                *previous = byte;
+                *flash = FLASH_FRAMES;
+            } else {
+                *flash = flash.saturating_sub(1);
+            }
+        }
+    }
+
+    ///
+    /// Whether the byte at the address was written during the last [`FLASH_FRAMES`] refreshes.
+    ///
+    pub fn is_flashing(&self, address: usize) -> bool {
+        self.flashes.get(address).is_some_and(|&flash| flash > 0)
+    }
+
+    ///
+    /// Lays out `rows` rows of `bytes_per_row` bytes, scrolling the least to keep the cursor
+    /// in sight.
+    ///
+    pub fn scroll_into_view(&mut self, rows: usize, bytes_per_row: usize) {
+        self.rows = rows.max(1);
+        self.bytes_per_row = bytes_per_row.max(1);
+        let row = self.cursor - self.cursor % self.bytes_per_row;
+        let last_row = (self.rows - 1) * self.bytes_per_row;
+        self.top -= self.top % self.bytes_per_row;
+        if row < self.top {
+            self.top = row;
+        } else if row > self.top + last_row {
+            self.top = row - last_row;
+        }
+    }
+
+    ///
+    /// Handles a key typed into the panel. Bytes are only edited when `editable`.
+    ///
+    pub fn input(&mut self, key: KeyCode, vm: &mut Chip8, editable: bool) -> Outcome {
+        let size = vm.memory.len();
+        if let Mode::GoTo(address) = &mut self.mode {
+            match key {
+                KeyCode::Char(digit) if digit.is_ascii_hexdigit() && address.len() < 4 => {
+                    address.push(digit)
+                }
+                KeyCode::Backspace => {
+                    address.pop();
+                }
+                KeyCode::Enter => {
+                    if let Ok(address) = usize::from_str_radix(address, 16) {
+                        self.move_to(address, size);
+                    }
+                    self.mode = Mode::Browse;
+                }
+                KeyCode::Esc => self.mode = Mode::Browse,
+                _ => {}
+            }
+            return Outcome::Handled;
+        }
+        let (row, page) = (self.bytes_per_row, self.bytes_per_row * self.rows);
+        let cursor = self.cursor;
+        match key {
+            KeyCode::Left => self.move_to(cursor.saturating_sub(1), size),
+            KeyCode::Right => self.move_to(cursor + 1, size),
+            KeyCode::Up if cursor >= row => self.move_to(cursor - row, size),
+            KeyCode::Down if cursor + row < size => self.move_to(cursor + row, size),
+            KeyCode::Up | KeyCode::Down => {}
+            KeyCode::PageUp => self.move_to(cursor.saturating_sub(page), size),
+            KeyCode::PageDown => self.move_to(cursor + page, size),
+            KeyCode::Home => self.move_to(0, size),
+            KeyCode::End => self.move_to(size - 1, size),
+            KeyCode::Char('g') => self.mode = Mode::GoTo(String::new()),
+            KeyCode::Char(digit) if digit.is_ascii_hexdigit() => {
+                if !editable {
+                    return Outcome::ReadOnly;
+                }
+                let nibble = digit.to_digit(16).unwrap_or_default() as u8;
+                match self.pending.take() {
+                    None => self.pending = Some(nibble),
+                    Some(high) => {
+                        vm.memory[cursor] = high << 4 | nibble;
+                        self.move_to(cursor + 1, size);
+                    }
+                }
+            }
+            KeyCode::Esc if self.pending.is_some() => self.pending = None,
+            KeyCode::Esc => return Outcome::Leave,
+            _ => return Outcome::Ignored,
+        }
+        Outcome::Handled
+    }
+
+    // Moves the cursor, within the memory, dropping the half typed byte.
+    fn move_to(&mut self, address: usize, size: usize) {
+        self.cursor = address.min(size - 1);
+        self.pending = None;
+    }
+}
+
+///
+/// Character shown for a byte in the ASCII column, `.` when not printable.
+///
+pub fn ascii(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        byte as char
+    } else {
+        '.'
+    }
+}
@@ -0,0 +1,256 @@
+use crate::chip8::Chip8;
+use crate::errors::EmulationError;
+use crate::keypad::Keypad;
+use crate::quirks::Quirks;
+use crate::state::{self, Reader, StateError, Writer};
+use crate::variant::Variant;
+use std::fmt;
+
+///
+/// First bytes of every movie file.
+///
+pub const MOVIE_MAGIC: [u8; 4] = *b"C8MV";
+
+///
+/// Version of the movie layout written by [`Movie::to_bytes`].
+///
+pub const MOVIE_VERSION: u16 = 1;
+
+///
+/// Error returned when a movie cannot be read or replayed.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovieError {
+    /// The data does not start with [`MOVIE_MAGIC`].
+    NotAMovie,
+    /// The movie was written by an unsupported version of the format.
+    UnsupportedVersion(u16),
+    /// The movie ends before all of its frames have been read.
+    Truncated,
+    /// The movie holds a value no recording can contain.
+    Corrupted(&'static str),
+    /// The movie was recorded while running another rom.
+    RomMismatch { expected: u64, found: u64 },
+    /// The screen at the end of the given frame, counted from 0, differs from the recording.
+    Desync {
+        frame: usize,
+        expected: u64,
+        found: u64,
+    },
+    /// The machine failed while replaying the movie.
+    Emulation(EmulationError),
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovieError::NotAMovie => write!(f, "not a movie"),
+            MovieError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported movie version {}, expected {}",
+                version, MOVIE_VERSION
+            ),
+            MovieError::Truncated => write!(f, "truncated movie"),
+            MovieError::Corrupted(reason) => write!(f, "corrupted movie: {}", reason),
+            MovieError::RomMismatch { expected, found } => write!(
+                f,
+                "movie recorded on another rom (hash {:016x}, running {:016x})",
+                found, expected
+            ),
+            MovieError::Desync {
+                frame,
+                expected,
+                found,
+            } => write!(
+                f,
+                "replay desynchronized at frame {}: screen checksum {:016x}, recorded {:016x}",
+                frame, found, expected
+            ),
+            MovieError::Emulation(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for MovieError {}
+
+impl From<StateError> for MovieError {
+    fn from(error: StateError) -> Self {
+        match error {
+            StateError::NotAState => MovieError::NotAMovie,
+            StateError::UnsupportedVersion(version) => MovieError::UnsupportedVersion(version),
+            StateError::Truncated => MovieError::Truncated,
+            StateError::Corrupted(reason) => MovieError::Corrupted(reason),
+            StateError::RomMismatch { expected, found } => {
+                MovieError::RomMismatch { expected, found }
+            }
+        }
+    }
+}
+
+impl From<EmulationError> for MovieError {
+    fn from(error: EmulationError) -> Self {
+        MovieError::Emulation(error)
+    }
+}
+
+///
+/// One recorded frame: the keys held down while it ran, and the screen it ended on.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MovieFrame {
+    pub keypad: Keypad,
+    pub checksum: u64,
+}
+
+///
+/// A recorded play session, replaying exactly as long as the machine is set up the same way:
+/// same rom, variant, quirks, random seed and speed. The screen checksum of every frame tells
+/// the exact frame a replay starts to differ at.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    pub rom_hash: u64,
+    pub variant: Variant,
+    pub quirks: Quirks,
+    pub seed: u64,
+    pub instructions_per_frame: usize,
+    pub frames: Vec<MovieFrame>,
+}
+
+///
+/// Checksum of a screen, as stored in movies.
+///
+pub fn checksum(framebuffer: &[u8]) -> u64 {
+    state::hash(framebuffer)
+}
+
+impl Movie {
+    ///
+    /// Starts recording the given machine, freshly loaded and seeded with `seed`.
+    ///
+    pub fn new(vm: &Chip8, seed: u64, instructions_per_frame: usize) -> Self {
+        Self {
+            rom_hash: vm.rom_hash,
+            variant: vm.variant,
+            quirks: vm.quirks,
+            seed,
+            instructions_per_frame,
+            frames: Vec::new(),
+        }
+    }
+
+    ///
+    /// Adds a frame the machine just ran with the given keys held down.
+    ///
+    pub fn record(&mut self, keypad: Keypad, vm: &Chip8) {
+        self.frames.push(MovieFrame {
+            keypad,
+            checksum: checksum(vm.framebuffer()),
+        });
+    }
+
+    ///
+    /// Creates the machine the movie was recorded on, running the given rom.
+    ///
+    pub fn machine(&self, rom: &[u8]) -> Result<Chip8, MovieError> {
+        let mut vm = Chip8::with_variant(self.variant, self.quirks);
+        vm.seed(self.seed);
+        vm.load(rom)?;
+        if vm.rom_hash != self.rom_hash {
+            return Err(MovieError::RomMismatch {
+                expected: vm.rom_hash,
+                found: self.rom_hash,
+            });
+        }
+        Ok(vm)
+    }
+
+    ///
+    /// Runs the given frame of the movie on a machine created by [`Movie::machine`],
+    /// having replayed all the frames before it, and checks the screen it ends on.
+    /// Panics when `frame` is past the end of the movie.
+    ///
+    pub fn replay_frame(&self, frame: usize, vm: &mut Chip8) -> Result<(), MovieError> {
+        let recorded = self.frames[frame];
+        vm.run_frame(recorded.keypad, self.instructions_per_frame)?;
+        let found = checksum(vm.framebuffer());
+        if found != recorded.checksum {
+            return Err(MovieError::Desync {
+                frame,
+                expected: recorded.checksum,
+                found,
+            });
+        }
+        Ok(())
+    }
+
+    ///
+    /// Replays the whole movie on the given rom, returning the machine as it ends.
+    ///
+    pub fn replay(&self, rom: &[u8]) -> Result<Chip8, MovieError> {
+        let mut vm = self.machine(rom)?;
+        for frame in 0..self.frames.len() {
+            self.replay_frame(frame, &mut vm)?;
+        }
+        Ok(vm)
+    }
+
+    ///
+    /// Writes the movie in the versioned format read back by [`Movie::from_bytes`].
+    ///
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer(Vec::with_capacity(32 + self.frames.len() * 10));
+        writer.0.extend_from_slice(&MOVIE_MAGIC);
+        writer.u16(MOVIE_VERSION);
+        writer.u64(self.rom_hash);
+        writer.u8(state::variant_to_byte(self.variant));
+        writer.u8(state::quirks_to_bits(self.quirks));
+        writer.u64(self.seed);
+        writer.u32(self.instructions_per_frame as u32);
+        writer.u32(self.frames.len() as u32);
+        for frame in &self.frames {
+            writer.u16(frame.keypad.bits());
+            writer.u64(frame.checksum);
+        }
+        writer.0
+    }
+
+    ///
+    /// Reads a movie written by [`Movie::to_bytes`].
+    ///
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MovieError> {
+        let mut reader = Reader(bytes);
+        if reader.take(MOVIE_MAGIC.len()).ok() != Some(&MOVIE_MAGIC[..]) {
+            return Err(MovieError::NotAMovie);
+        }
+        let version = reader.u16()?;
+        if version != MOVIE_VERSION {
+            return Err(MovieError::UnsupportedVersion(version));
+        }
+        let rom_hash = reader.u64()?;
+        let variant = state::variant_from_byte(reader.u8()?)?;
+        let quirks = state::quirks_from_bits(reader.u8()?);
+        let seed = reader.u64()?;
+        let instructions_per_frame = reader.u32()? as usize;
+        let frame_count = reader.u32()? as usize;
+        let frames = (0..frame_count)
+            .map(|_| {
+                Ok(MovieFrame {
+                    keypad: Keypad::from_bits(reader.u16()?),
+                    checksum: reader.u64()?,
+                })
+            })
+            .collect::<Result<_, StateError>>()?;
+        if !reader.0.is_empty() {
+            return Err(MovieError::Corrupted("trailing data"));
+        }
+        Ok(Self {
+            rom_hash,
+            variant,
+            quirks,
+            seed,
+            instructions_per_frame,
+            frames,
+        })
+    }
+}
@@ -0,0 +1,156 @@
+use crate::chip8::Chip8;
+use crate::render::Screen;
+use std::fmt;
+use std::str::FromStr;
+
+///
+/// Frames a cleared pixel takes to fade out, when not configured.
+///
+pub const DEFAULT_DECAY_FRAMES: u8 = 4;
+
+///
+/// How pixels persist on the screen after being cleared, hiding the flicker of sprites being
+/// erased then drawn again to move them.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Persistence {
+    /// Pixels go dark as soon as they are cleared.
+    #[default]
+    Off,
+    /// Cleared pixels fade out over a few frames, like the phosphor of a CRT.
+    Decay,
+    /// Pixels lit in the previous frame stay lit, the last two frames being combined.
+    Blend,
+}
+
+///
+/// Error returned when parsing an unknown persistence name.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPersistence(pub String);
+
+impl fmt::Display for UnknownPersistence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown persistence '{}', expected one of: off, decay, blend",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownPersistence {}
+
+impl FromStr for Persistence {
+    type Err = UnknownPersistence;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "off" => Ok(Persistence::Off),
+            "decay" => Ok(Persistence::Decay),
+            "blend" => Ok(Persistence::Blend),
+            _ => Err(UnknownPersistence(name.to_string())),
+        }
+    }
+}
+
+///
+/// Filter between the framebuffer of the machine and the renderer, keeping pixels visible
+/// for a while after they are cleared.
+///
+/// ```
+/// use rusty_chip8::phosphor::{Persistence, Phosphor};
+/// use rusty_chip8::Chip8;
+///
+/// let mut vm = Chip8::default();
+/// let mut phosphor = Phosphor::new(Persistence::Decay, 2);
+/// vm.gfx[0] = 1;
+/// phosphor.update(&vm);
+/// vm.gfx[0] = 0;
+/// phosphor.update(&vm);
+/// assert_eq!(phosphor.screen().pixels[0], 1);
+/// assert_eq!(phosphor.screen().brightness.unwrap()[0], 170);
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct Phosphor {
+    pub persistence: Persistence,
+    /// Frames a cleared pixel takes to fade out in decay mode.
+    pub decay_frames: u8,
+    width: usize,
+    height: usize,
+    // Planes each pixel is, or was last, lit on.
+    pixels: Vec<u8>,
+    brightness: Vec<u8>,
+    // Frames left before each cleared pixel goes dark in decay mode, or the pixels of the
+    // previous frame in blend mode.
+    history: Vec<u8>,
+}
+
+impl Phosphor {
+    pub fn new(persistence: Persistence, decay_frames: u8) -> Self {
+        Self {
+            persistence,
+            decay_frames,
+            width: 0,
+            height: 0,
+            pixels: Vec::new(),
+            brightness: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+
+    ///
+    /// Takes in the screen of the machine, to be called once per frame.
+    ///
+    pub fn update(&mut self, vm: &Chip8) {
+        let framebuffer = vm.framebuffer();
+        if (self.width, self.height) != (vm.width(), vm.height()) {
+            // Nothing persists across resolution changes.
+            self.width = vm.width();
+            self.height = vm.height();
+            self.pixels = framebuffer.to_vec();
+            self.brightness = vec![u8::MAX; framebuffer.len()];
+            self.history = vec![0; framebuffer.len()];
+        }
+        let frames = self.decay_frames as u16;
+        for (index, &pixel) in framebuffer.iter().enumerate() {
+            match self.persistence {
+                Persistence::Off => {
+                    self.pixels[index] = pixel;
+                    self.brightness[index] = u8::MAX;
+                }
+                Persistence::Decay if pixel != 0 => {
+                    self.pixels[index] = pixel;
+                    self.brightness[index] = u8::MAX;
+                    self.history[index] = self.decay_frames;
+                }
+                Persistence::Decay => {
+                    let left = self.history[index] as u16;
+                    self.brightness[index] = (left * 255 / (frames + 1)) as u8;
+                    self.history[index] = self.history[index].saturating_sub(1);
+                    if left == 0 {
+                        self.pixels[index] = 0;
+                    }
+                }
+                Persistence::Blend => {
+                    self.pixels[index] = pixel | self.history[index];
+                    self.brightness[index] = u8::MAX;
+                    self.history[index] = pixel;
+                }
+            }
+        }
+    }
+
+    ///
+    /// Returns the screen to render, as of the last update.
+    ///
+    pub fn screen(&self) -> Screen<'_> {
+        Screen {
+            pixels: &self.pixels,
+            width: self.width,
+            height: self.height,
+            brightness: Some(&self.brightness),
+        }
+    }
+}
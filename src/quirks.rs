@@ -0,0 +1,134 @@
+use std::fmt;
+use std::str::FromStr;
+
+///
+/// Behaviours of the instructions whose semantic changed between Chip8 implementations.
+///
+/// Roms are usually written against one specific interpreter, and only run correctly when the
+/// matching profile is selected. See <https://chip8.gulrak.net/#quirks> for the details.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8XY6 / 8XYE shift VY and store the result into VX, instead of shifting VX in place.
+    pub shift_uses_vy: bool,
+    /// How far FX55 / FX65 move I past the registers they store or load.
+    pub index_increment: IndexIncrement,
+    /// BNNN jumps to XNN + VX instead of NNN + V0.
+    pub jump_uses_vx: bool,
+    /// 8XY1 / 8XY2 / 8XY3 reset VF to 0.
+    pub vf_reset: bool,
+    /// DXYN clips sprites at the screen edges instead of wrapping them to the other side.
+    pub clip_sprites: bool,
+    /// DXYN waits for the next 60Hz display refresh before the following instruction runs.
+    pub display_wait: bool,
+}
+
+///
+/// How far FX55 / FX65 move I past the registers VX they store or load.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexIncrement {
+    /// I is left unchanged.
+    None,
+    /// I is left pointing at the last register stored or loaded.
+    ByX,
+    /// I is left pointing right after the last register stored or loaded.
+    ByXPlusOne,
+}
+
+impl Quirks {
+    ///
+    /// The original interpreter, running on the RCA COSMAC VIP.
+    ///
+    pub const fn cosmac_vip() -> Self {
+        Self {
+            shift_uses_vy: true,
+            index_increment: IndexIncrement::ByXPlusOne,
+            jump_uses_vx: false,
+            vf_reset: true,
+            clip_sprites: true,
+            display_wait: true,
+        }
+    }
+
+    ///
+    /// CHIP-48, the HP48 calculators interpreter, whose FX55 / FX65 advance I by X only.
+    ///
+    pub const fn chip48() -> Self {
+        Self {
+            shift_uses_vy: false,
+            index_increment: IndexIncrement::ByX,
+            jump_uses_vx: true,
+            vf_reset: false,
+            clip_sprites: true,
+            display_wait: false,
+        }
+    }
+
+    ///
+    /// SUPER-CHIP 1.1, the successor of CHIP-48 on the HP48 calculators, whose FX55 / FX65
+    /// leave I unchanged.
+    ///
+    pub const fn super_chip() -> Self {
+        Self {
+            shift_uses_vy: false,
+            index_increment: IndexIncrement::None,
+            jump_uses_vx: true,
+            vf_reset: false,
+            clip_sprites: true,
+            display_wait: false,
+        }
+    }
+
+    ///
+    /// XO-CHIP, as implemented by Octo.
+    ///
+    pub const fn xo_chip() -> Self {
+        Self {
+            shift_uses_vy: true,
+            index_increment: IndexIncrement::ByXPlusOne,
+            jump_uses_vx: false,
+            vf_reset: false,
+            clip_sprites: false,
+            display_wait: false,
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::cosmac_vip()
+    }
+}
+
+///
+/// Error returned when parsing an unknown quirks profile name.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownProfile(pub String);
+
+impl fmt::Display for UnknownProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown quirks profile '{}', expected one of: vip, chip48, schip, xochip",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownProfile {}
+
+impl FromStr for Quirks {
+    type Err = UnknownProfile;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "vip" | "cosmac-vip" | "chip8" => Ok(Self::cosmac_vip()),
+            "chip48" | "chip-48" => Ok(Self::chip48()),
+            "schip" | "superchip" | "super-chip" => Ok(Self::super_chip()),
+            "xochip" | "xo-chip" => Ok(Self::xo_chip()),
+            _ => Err(UnknownProfile(name.to_string())),
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use crate::chip8::Chip8;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::Color;
+use ratatui::symbols::Marker;
+use ratatui::widgets::canvas::{Canvas, Points};
+use ratatui::widgets::Widget;
+use std::fmt;
+use std::str::FromStr;
+
+///
+/// Pixels of a screen, one byte per pixel holding one bit per plane, row after row.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Screen<'a> {
+    pub pixels: &'a [u8],
+    pub width: usize,
+    pub height: usize,
+    /// Brightness of each pixel from 0 to 255, every pixel being fully bright when `None`.
+    /// Pixels fading out keep the planes they were lit on.
+    pub brightness: Option<&'a [u8]>,
+}
+
+impl<'a> Screen<'a> {
+    ///
+    /// Returns the pixel at the given coordinates, unlit outside of the screen.
+    ///
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x]
+        } else {
+            0
+        }
+    }
+
+    ///
+    /// Returns the colour of the pixel at the given coordinates, `None` when it is not lit.
+    ///
+    pub fn color(&self, palette: &Palette, x: usize, y: usize) -> Option<Color> {
+        palette.shade(self.pixel(x, y), self.brightness(x, y))
+    }
+
+    fn brightness(&self, x: usize, y: usize) -> u8 {
+        match self.brightness {
+            Some(brightness) if x < self.width && y < self.height => brightness[y * self.width + x],
+            _ => u8::MAX,
+        }
+    }
+}
+
+impl<'a> From<&'a Chip8> for Screen<'a> {
+    fn from(vm: &'a Chip8) -> Self {
+        Self {
+            pixels: vm.framebuffer(),
+            width: vm.width(),
+            height: vm.height(),
+            brightness: None,
+        }
+    }
+}
+
+///
+/// Colours of the screen: the background, then the pixels lit on plane 1, plane 2, and both.
+///
+/// [`Color::Reset`] stands for the default colour of the terminal, its foreground colour when
+/// used for lit pixels.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub background: Color,
+    pub planes: [Color; 3],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
+
+impl Palette {
+    ///
+    /// The colours of the terminal, with plane 2 in red and both planes in yellow.
+    ///
+    pub const CLASSIC: Palette = Palette {
+        background: Color::Reset,
+        planes: [Color::Reset, Color::LightRed, Color::Yellow],
+    };
+
+    ///
+    /// Returns the colour of a pixel, `None` when it is not lit.
+    ///
+    pub fn color(&self, pixel: u8) -> Option<Color> {
+        match pixel & 0x3 {
+            0 => None,
+            planes => Some(self.planes[planes as usize - 1]),
+        }
+    }
+
+    ///
+    /// Returns the colour of a pixel dimmed to the given brightness, from 0 to 255, `None` when
+    /// it is not lit or completely dark.
+    ///
+    /// RGB colours are mixed with the background, named colours darkened in a couple of steps.
+    ///
+    pub fn shade(&self, pixel: u8, brightness: u8) -> Option<Color> {
+        let color = self.color(pixel)?;
+        if brightness == u8::MAX {
+            return Some(color);
+        } else if brightness == 0 {
+            return None;
+        }
+        let mix = |from: u8, to: u8| {
+            let brightness = brightness as u16;
+            ((from as u16 * brightness + to as u16 * (255 - brightness)) / 255) as u8
+        };
+        Some(match (color, self.background) {
+            (Color::Rgb(r, g, b), Color::Rgb(to_r, to_g, to_b)) => {
+                Color::Rgb(mix(r, to_r), mix(g, to_g), mix(b, to_b))
+            }
+            (Color::Rgb(r, g, b), _) => Color::Rgb(mix(r, 0), mix(g, 0), mix(b, 0)),
+            (color, _) if brightness >= 128 => darker(color),
+            _ => Color::DarkGray,
+        })
+    }
+}
+
+// Darker shade of a named colour.
+fn darker(color: Color) -> Color {
+    match color {
+        Color::LightRed => Color::Red,
+        Color::LightGreen => Color::Green,
+        Color::LightYellow => Color::Yellow,
+        Color::LightBlue => Color::Blue,
+        Color::LightMagenta => Color::Magenta,
+        Color::LightCyan => Color::Cyan,
+        Color::Reset | Color::White => Color::Gray,
+        _ => Color::DarkGray,
+    }
+}
+
+///
+/// Draws screens into terminal cells.
+///
+pub trait Renderer {
+    ///
+    /// Returns the columns and rows of terminal cells taken by a screen of the given size, or
+    /// `None` when the renderer stretches it to any area.
+    ///
+    fn size(&self, width: usize, height: usize) -> Option<(u16, u16)>;
+
+    ///
+    /// Draws the screen at the top left of the area, clipped to it.
+    ///
+    fn render(&self, screen: Screen<'_>, palette: &Palette, area: Rect, buffer: &mut Buffer);
+}
+
+// Draws cells of `columns` by `rows` pixels, `cell` returning the symbol and the foreground and
+// background colours of the cell at the given pixel coordinates.
+fn render_cells(
+    screen: Screen<'_>,
+    (columns, rows): (usize, usize),
+    area: Rect,
+    buffer: &mut Buffer,
+    cell: impl Fn(usize, usize) -> (char, Color, Color),
+) {
+    let width = screen.width.div_ceil(columns).min(area.width as usize);
+    let height = screen.height.div_ceil(rows).min(area.height as usize);
+    for row in 0..height {
+        for column in 0..width {
+            let (symbol, foreground, background) = cell(column * columns, row * rows);
+            buffer[(area.x + column as u16, area.y + row as u16)]
+                .set_char(symbol)
+                .set_fg(foreground)
+                .set_bg(background);
+        }
+    }
+}
+
+///
+/// Draws two rows of pixels per terminal cell with the `▀` and `▄` characters, their upper
+/// and lower halves taking the foreground and background colours: pixels come out square on
+/// most terminal fonts, the 64x32 screen taking 64 columns and 16 rows.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HalfBlock;
+
+impl Renderer for HalfBlock {
+    fn size(&self, width: usize, height: usize) -> Option<(u16, u16)> {
+        Some((width as u16, height.div_ceil(2) as u16))
+    }
+
+    fn render(&self, screen: Screen<'_>, palette: &Palette, area: Rect, buffer: &mut Buffer) {
+        let background = palette.background;
+        render_cells(screen, (1, 2), area, buffer, |x, y| {
+            let top = screen.color(palette, x, y);
+            let bottom = screen.color(palette, x, y + 1);
+            match (top, bottom) {
+                (None, None) => (' ', background, background),
+                (Some(top), None) => ('▀', top, background),
+                (None, Some(bottom)) => ('▄', bottom, background),
+                (Some(top), Some(bottom)) if top == bottom => ('█', top, background),
+                // As a background colour, the default colour of the terminal is its
+                // background one: lit pixels of that colour take the foreground.
+                (Some(top), Some(Color::Reset)) => ('▄', Color::Reset, top),
+                (Some(top), Some(bottom)) => ('▀', top, bottom),
+            }
+        });
+    }
+}
+
+///
+/// Draws 2x4 pixels per terminal cell with braille patterns, for tiny terminals: the 64x32
+/// screen takes 32 columns and 8 rows. A cell having a single colour, it takes the one of its
+/// brightest pixel, lit on the most planes.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Braille;
+
+// Dot of each pixel of a 2x4 cell, indexed by row then column.
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+impl Renderer for Braille {
+    fn size(&self, width: usize, height: usize) -> Option<(u16, u16)> {
+        Some((width.div_ceil(2) as u16, height.div_ceil(4) as u16))
+    }
+
+    fn render(&self, screen: Screen<'_>, palette: &Palette, area: Rect, buffer: &mut Buffer) {
+        render_cells(screen, (2, 4), area, buffer, |x, y| {
+            let (mut dots, mut brightest) = (0, (0, 0));
+            for (row, row_dots) in BRAILLE_DOTS.iter().enumerate() {
+                for (column, dot) in row_dots.iter().enumerate() {
+                    let (x, y) = (x + column, y + row);
+                    if screen.color(palette, x, y).is_some() {
+                        dots |= dot;
+                        brightest = brightest.max((screen.brightness(x, y), screen.pixel(x, y)));
+                    }
+                }
+            }
+            let symbol = char::from_u32(0x2800 + dots).unwrap_or(' ');
+            let foreground = palette
+                .shade(brightest.1, brightest.0)
+                .unwrap_or(palette.background);
+            (symbol, foreground, palette.background)
+        });
+    }
+}
+
+///
+/// Draws the screen on a ratatui canvas, stretched to the whole area.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlockCanvas;
+
+///
+/// Returns points in the canvas screen referential, grouped by colour.
+/// Chip8 have a top left coordinates being (0,0),
+/// while ratatui works with the bottom left coordinates being (0,0)
+///
+fn as_points(screen: Screen<'_>, palette: &Palette) -> Vec<(Color, Vec<(f64, f64)>)> {
+    let mut points: Vec<(Color, Vec<(f64, f64)>)> = Vec::new();
+    for y in 0..screen.height {
+        for x in 0..screen.width {
+            let color = match screen.color(palette, x, y) {
+                Some(color) => color,
+                None => continue,
+            };
+            let point = (x as f64, (screen.height - y) as f64);
+            match points.iter_mut().find(|(existing, _)| *existing == color) {
+                Some((_, coords)) => coords.push(point),
+                None => points.push((color, vec![point])),
+            }
+        }
+    }
+    points
+}
+
+impl Renderer for BlockCanvas {
+    fn size(&self, _: usize, _: usize) -> Option<(u16, u16)> {
+        None
+    }
+
+    fn render(&self, screen: Screen<'_>, palette: &Palette, area: Rect, buffer: &mut Buffer) {
+        let points = as_points(screen, palette);
+        Canvas::default()
+            .marker(Marker::Block)
+            .background_color(palette.background)
+            .x_bounds([0.0, screen.width as f64])
+            .y_bounds([0.0, screen.height as f64])
+            .paint(move |ctx| {
+                for (color, coords) in &points {
+                    ctx.draw(&Points {
+                        coords,
+                        color: *color,
+                    });
+                }
+            })
+            .render(area, buffer);
+    }
+}
+
+///
+/// The renderers to choose from.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RendererKind {
+    #[default]
+    HalfBlock,
+    Braille,
+    Canvas,
+}
+
+impl RendererKind {
+    pub fn renderer(self) -> Box<dyn Renderer> {
+        match self {
+            RendererKind::HalfBlock => Box::new(HalfBlock),
+            RendererKind::Braille => Box::new(Braille),
+            RendererKind::Canvas => Box::new(BlockCanvas),
+        }
+    }
+}
+
+///
+/// Error returned when parsing an unknown renderer name.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownRenderer(pub String);
+
+impl fmt::Display for UnknownRenderer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown renderer '{}', expected one of: half-block, braille, canvas",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownRenderer {}
+
+impl FromStr for RendererKind {
+    type Err = UnknownRenderer;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "half-block" | "halfblock" => Ok(RendererKind::HalfBlock),
+            "braille" => Ok(RendererKind::Braille),
+            "canvas" => Ok(RendererKind::Canvas),
+            _ => Err(UnknownRenderer(name.to_string())),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::chip8::Chip8;
+use std::collections::VecDeque;
+
+///
+/// Frames the emulator keeps for rewinding when not configured, 10 seconds at 60Hz.
+///
+pub const DEFAULT_REWIND_FRAMES: usize = 600;
+
+// Unchanged bytes between two changed ones below which both land in the same run,
+// a run header costing more than a few bytes copied along.
+const MERGE_GAP: usize = 8;
+
+///
+/// Changes turning a snapshot into the one taken the frame before.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Patch {
+    // The snapshots differ in size, after a resolution change for instance.
+    Full(Vec<u8>),
+    // Bytes to write back, by offset.
+    Runs(Vec<(usize, Vec<u8>)>),
+}
+
+impl Patch {
+    fn between(from: &[u8], to: &[u8]) -> Self {
+        if from.len() != to.len() {
+            return Patch::Full(to.to_vec());
+        }
+        let mut runs: Vec<(usize, Vec<u8>)> = Vec::new();
+        for (offset, (&old, &new)) in from.iter().zip(to).enumerate() {
+            if old == new {
+                continue;
+            }
+            match runs.last_mut() {
+                Some((start, bytes)) if offset - (*start + bytes.len()) <= MERGE_GAP => {
+                    bytes.extend_from_slice(&to[*start + bytes.len()..=offset]);
+                }
+                _ => runs.push((offset, vec![new])),
+            }
+        }
+        Patch::Runs(runs)
+    }
+
+    fn apply(self, snapshot: &mut Vec<u8>) {
+        match self {
+            Patch::Full(bytes) => *snapshot = bytes,
+            Patch::Runs(runs) => {
+                for (start, bytes) in runs {
+                    snapshot[start..start + bytes.len()].copy_from_slice(&bytes);
+                }
+            }
+        }
+    }
+}
+
+///
+/// Bounded history of machine snapshots, one per frame, used to rewind the machine.
+///
+/// Only the latest snapshot is stored whole, each older one is a patch against the snapshot
+/// taken the frame after: the memory rarely changes much from one frame to the next, so a
+/// frame usually costs a few dozen bytes. Once full, the oldest frames are dropped.
+///
+/// The default history keeps no frame, rewinding being opt-in.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct History {
+    capacity: usize,
+    latest: Vec<u8>,
+    // Oldest first.
+    patches: VecDeque<Patch>,
+}
+
+impl History {
+    ///
+    /// Creates a history keeping up to `capacity` frames, 0 disabling it.
+    ///
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            latest: Vec::new(),
+            patches: VecDeque::new(),
+        }
+    }
+
+    ///
+    /// Maximum number of frames that can be rewound.
+    ///
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    ///
+    /// Number of frames that can currently be rewound.
+    ///
+    pub fn len(&self) -> usize {
+        self.patches.len()
+    }
+
+    ///
+    /// Returns whether there is no frame to rewind to.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.patches.is_empty()
+    }
+
+    ///
+    /// Number of snapshot bytes held, patches included.
+    ///
+    pub fn stored_bytes(&self) -> usize {
+        let patches = self.patches.iter().map(|patch| match patch {
+            Patch::Full(bytes) => bytes.len(),
+            Patch::Runs(runs) => runs.iter().map(|(_, bytes)| bytes.len()).sum(),
+        });
+        self.latest.len() + patches.sum::<usize>()
+    }
+
+    ///
+    /// Forgets every snapshot.
+    ///
+    pub fn clear(&mut self) {
+        self.latest.clear();
+        self.patches.clear();
+    }
+
+    ///
+    /// Adds the snapshot of the frame that just ended.
+    ///
+    pub fn record(&mut self, snapshot: Vec<u8>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if !self.latest.is_empty() {
+            self.patches
+                .push_back(Patch::between(&snapshot, &self.latest));
+            if self.patches.len() > self.capacity {
+                self.patches.pop_front();
+            }
+        }
+        self.latest = snapshot;
+    }
+
+    ///
+    /// Drops the last `frames` snapshots, or as many as available, and returns the number
+    /// dropped along with the snapshot now being the latest, if any.
+    ///
+    pub fn rewind(&mut self, frames: usize) -> (usize, Option<&[u8]>) {
+        let frames = frames.min(self.patches.len());
+        for _ in 0..frames {
+            if let Some(patch) = self.patches.pop_back() {
+                patch.apply(&mut self.latest);
+            }
+        }
+        let latest = Some(self.latest.as_slice()).filter(|latest| !latest.is_empty());
+        (frames, latest)
+    }
+}
+
+impl Chip8 {
+    ///
+    /// Brings the machine back to where it was `frames` frames ago, as recorded by
+    /// [`Chip8::run_frame`] in [`Chip8::history`], and returns the number of frames actually
+    /// rewound: fewer when the history does not go back that far.
+    ///
+    pub fn rewind(&mut self, frames: usize) -> usize {
+        let mut history = std::mem::take(&mut self.history);
+        let (rewound, snapshot) = history.rewind(frames);
+        // Snapshots are taken from this very machine, and dropped when another rom is
+        // loaded: restoring them cannot fail.
+        let restored = snapshot.map_or(Ok(()), |snapshot| self.load_state(snapshot));
+        self.history = history;
+        if restored.is_ok() {
+            rewound
+        } else {
+            0
+        }
+    }
+}
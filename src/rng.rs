@@ -0,0 +1,51 @@
+///
+/// Small deterministic pseudo random number generator used by CXNN (SplitMix64).
+///
+/// Its whole state is a single `u64`, so a machine can be cloned, saved and replayed
+/// bit for bit: the same seed always produces the same sequence.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    ///
+    /// Creates a generator starting from the given seed.
+    ///
+    pub const fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    ///
+    /// Creates a generator seeded from the operating system entropy.
+    ///
+    pub fn from_entropy() -> Self {
+        Self::new(rand::random())
+    }
+
+    ///
+    /// Returns the internal state, which can be given back to [`Rng::new`] to resume the sequence.
+    ///
+    pub const fn state(&self) -> u64 {
+        self.state
+    }
+
+    ///
+    /// Returns the next 64 bits of the sequence.
+    ///
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    ///
+    /// Returns the next random byte.
+    ///
+    pub fn next_u8(&mut self) -> u8 {
+        (self.next_u64() >> 56) as u8
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::{Duration, Instant};
+
+///
+/// Rate at which the timers are decremented and the screen is refreshed, in Hz.
+///
+pub const FRAME_RATE: u32 = 60;
+
+///
+/// Instructions executed per frame when nothing else is asked,
+/// roughly matching the speed of the COSMAC VIP interpreter.
+///
+pub const DEFAULT_INSTRUCTIONS_PER_FRAME: usize = 11;
+
+///
+/// Frames are never caught up beyond this count, so a stalled host does not make
+/// the game run in fast forward once it resumes.
+///
+const MAX_FRAMES_BEHIND: u32 = 4;
+
+///
+/// Returns the number of instructions to run per frame to reach the given clock speed.
+///
+pub fn instructions_per_frame(hz: u32) -> usize {
+    (hz / FRAME_RATE).max(1) as usize
+}
+
+///
+/// Keeps the emulation running at [`FRAME_RATE`], independently of how long
+/// the host takes to emulate and render each frame.
+///
+pub struct Scheduler {
+    frame_duration: Duration,
+    next_frame: Instant,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new(Instant::now())
+    }
+}
+
+impl Scheduler {
+    ///
+    /// Creates a scheduler whose first frame is due at `start`.
+    ///
+    pub fn new(start: Instant) -> Self {
+        Self {
+            frame_duration: Duration::from_secs(1) / FRAME_RATE,
+            next_frame: start,
+        }
+    }
+
+    ///
+    /// Returns how many frames should be emulated at `now`, and schedules the next one.
+    ///
+    pub fn frames_due(&mut self, now: Instant) -> u32 {
+        let mut frames = 0;
+        while self.next_frame <= now {
+            self.next_frame += self.frame_duration;
+            frames += 1;
+        }
+        if frames > MAX_FRAMES_BEHIND {
+            self.next_frame = now + self.frame_duration;
+            frames = MAX_FRAMES_BEHIND;
+        }
+        frames
+    }
+
+    ///
+    /// Returns how long to wait from `now` until the next frame is due.
+    ///
+    pub fn until_next_frame(&self, now: Instant) -> Duration {
+        self.next_frame.saturating_duration_since(now)
+    }
+}
@@ -0,0 +1,316 @@
+use crate::chip8::{Chip8, MEMORY_SIZE, XO_CHIP_MEMORY_SIZE};
+use crate::quirks::{IndexIncrement, Quirks};
+use crate::rng::Rng;
+use crate::variant::Variant;
+use std::fmt;
+
+///
+/// First bytes of every save state.
+///
+pub const STATE_MAGIC: [u8; 4] = *b"C8ST";
+
+///
+/// Version of the save state layout written by [`Chip8::save_state`].
+/// States written by another version are refused.
+///
+pub const STATE_VERSION: u16 = 2;
+
+///
+/// Error returned when a save state cannot be restored.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    /// The data does not start with [`STATE_MAGIC`].
+    NotAState,
+    /// The state was written by an unsupported version of the format.
+    UnsupportedVersion(u16),
+    /// The state ends before the whole machine has been read.
+    Truncated,
+    /// The state holds a value no machine can be in.
+    Corrupted(&'static str),
+    /// The state was captured while running another rom.
+    RomMismatch { expected: u64, found: u64 },
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::NotAState => write!(f, "not a save state"),
+            StateError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported save state version {}, expected {}",
+                version, STATE_VERSION
+            ),
+            StateError::Truncated => write!(f, "truncated save state"),
+            StateError::Corrupted(reason) => write!(f, "corrupted save state: {}", reason),
+            StateError::RomMismatch { expected, found } => write!(
+                f,
+                "save state belongs to another rom (hash {:016x}, running {:016x})",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+///
+/// 64 bits FNV-1a hash, telling which rom a save state or a movie belongs to,
+/// and checking the screens of a movie.
+///
+pub fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+// One bit per quirk, from the lowest bit in declaration order, then the quirks added since.
+// I moving by X + 1 and by X have one bit each, none of them being set when I is left unchanged.
+pub(crate) fn quirks_to_bits(quirks: Quirks) -> u8 {
+    [
+        quirks.shift_uses_vy,
+        quirks.index_increment == IndexIncrement::ByXPlusOne,
+        quirks.jump_uses_vx,
+        quirks.vf_reset,
+        quirks.clip_sprites,
+        quirks.display_wait,
+        quirks.index_increment == IndexIncrement::ByX,
+    ]
+    .iter()
+    .rev()
+    .fold(0, |bits, &enabled| bits << 1 | enabled as u8)
+}
+
+pub(crate) fn quirks_from_bits(bits: u8) -> Quirks {
+    Quirks {
+        shift_uses_vy: bits & 0x01 != 0,
+        index_increment: if bits & 0x02 != 0 {
+            IndexIncrement::ByXPlusOne
+        } else if bits & 0x40 != 0 {
+            IndexIncrement::ByX
+        } else {
+            IndexIncrement::None
+        },
+        jump_uses_vx: bits & 0x04 != 0,
+        vf_reset: bits & 0x08 != 0,
+        clip_sprites: bits & 0x10 != 0,
+        display_wait: bits & 0x20 != 0,
+    }
+}
+
+pub(crate) fn variant_to_byte(variant: Variant) -> u8 {
+    match variant {
+        Variant::Chip8 => 0,
+        Variant::SuperChip => 1,
+        Variant::XoChip => 2,
+    }
+}
+
+pub(crate) fn variant_from_byte(byte: u8) -> Result<Variant, StateError> {
+    match byte {
+        0 => Ok(Variant::Chip8),
+        1 => Ok(Variant::SuperChip),
+        2 => Ok(Variant::XoChip),
+        _ => Err(StateError::Corrupted("unknown variant")),
+    }
+}
+
+// Little endian writer of the state fields.
+pub(crate) struct Writer(pub(crate) Vec<u8>);
+
+impl Writer {
+    pub(crate) fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    pub(crate) fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    // Variable length data, prefixed by its length.
+    pub(crate) fn bytes(&mut self, bytes: &[u8]) {
+        self.u32(bytes.len() as u32);
+        self.0.extend_from_slice(bytes);
+    }
+}
+
+pub(crate) struct Reader<'a>(pub(crate) &'a [u8]);
+
+impl<'a> Reader<'a> {
+    pub(crate) fn take(&mut self, length: usize) -> Result<&'a [u8], StateError> {
+        if self.0.len() < length {
+            return Err(StateError::Truncated);
+        }
+        let (taken, rest) = self.0.split_at(length);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    pub(crate) fn array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, StateError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, StateError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn bool(&mut self) -> Result<bool, StateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Corrupted("invalid boolean")),
+        }
+    }
+
+    pub(crate) fn bytes(&mut self) -> Result<&'a [u8], StateError> {
+        let length = self.u32()? as usize;
+        self.take(length)
+    }
+}
+
+impl Chip8 {
+    ///
+    /// Captures the whole machine, quirks and rom hash included, in the versioned save state
+    /// format read back by [`Chip8::load_state`].
+    ///
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut writer = Writer(Vec::with_capacity(self.memory.len() + self.gfx.len() + 128));
+        writer.0.extend_from_slice(&STATE_MAGIC);
+        writer.u16(STATE_VERSION);
+        writer.u64(self.rom_hash);
+        writer.u8(variant_to_byte(self.variant));
+        writer.u8(quirks_to_bits(self.quirks));
+        writer.u16(self.op_code);
+        writer.u16(self.program_counter);
+        writer.u16(self.memory_index);
+        writer.0.extend_from_slice(&self.register);
+        writer.u8(self.delay_timer);
+        writer.u8(self.sound_timer);
+        writer.bool(self.buzzer);
+        writer.u32(self.sound_frames);
+        writer.u64(self.rng.state());
+        writer.u32(self.stack_limit as u32);
+        writer.u32(self.stack.len() as u32);
+        self.stack.iter().for_each(|&address| writer.u16(address));
+        writer.bool(self.hires);
+        writer.u8(self.selected_planes);
+        writer.0.extend_from_slice(&self.audio_pattern);
+        writer.u8(self.pitch);
+        writer.0.extend_from_slice(&self.rpl_flags);
+        writer.bool(self.exited);
+        writer.bool(self.should_draw);
+        // 0xFF when FX0A is not waiting for a key release.
+        writer.u8(self.awaited_key.unwrap_or(0xFF));
+        writer.bytes(&self.gfx);
+        writer.bytes(&self.memory);
+        writer.0
+    }
+
+    ///
+    /// Restores a machine captured by [`Chip8::save_state`].
+    ///
+    /// The state is refused when it was captured while running another rom than the one
+    /// currently loaded. On error, the machine is left untouched.
+    /// The rewind history is kept, the restored machine can be rewound to before the load.
+    ///
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let mut reader = Reader(state);
+        if reader.take(STATE_MAGIC.len()).ok() != Some(&STATE_MAGIC[..]) {
+            return Err(StateError::NotAState);
+        }
+        let version = reader.u16()?;
+        if version != STATE_VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        let found = reader.u64()?;
+        if found != self.rom_hash {
+            return Err(StateError::RomMismatch {
+                expected: self.rom_hash,
+                found,
+            });
+        }
+        let variant = variant_from_byte(reader.u8()?)?;
+        let mut machine = Chip8::with_variant(variant, quirks_from_bits(reader.u8()?));
+        machine.rom_hash = found;
+        machine.op_code = reader.u16()?;
+        machine.program_counter = reader.u16()?;
+        machine.memory_index = reader.u16()?;
+        machine.register = reader.array()?;
+        machine.delay_timer = reader.u8()?;
+        machine.sound_timer = reader.u8()?;
+        machine.buzzer = reader.bool()?;
+        machine.sound_frames = reader.u32()?;
+        machine.rng = Rng::new(reader.u64()?);
+        machine.stack_limit = reader.u32()? as usize;
+        let stack_length = reader.u32()? as usize;
+        if stack_length > machine.stack_limit {
+            return Err(StateError::Corrupted("stack deeper than its limit"));
+        }
+        machine.stack = (0..stack_length)
+            .map(|_| reader.u16())
+            .collect::<Result<_, _>>()?;
+        machine.hires = reader.bool()?;
+        machine.selected_planes = reader.u8()?;
+        machine.audio_pattern = reader.array()?;
+        machine.pitch = reader.u8()?;
+        machine.rpl_flags = reader.array()?;
+        machine.exited = reader.bool()?;
+        machine.should_draw = reader.bool()?;
+        machine.awaited_key = match reader.u8()? {
+            0xFF => None,
+            key if key < 16 => Some(key),
+            _ => return Err(StateError::Corrupted("invalid awaited key")),
+        };
+        let gfx = reader.bytes()?;
+        if gfx.len() != machine.width() * machine.height() {
+            return Err(StateError::Corrupted("framebuffer size mismatch"));
+        }
+        machine.gfx = gfx.to_vec();
+        let memory = reader.bytes()?;
+        let memory_size = match variant {
+            Variant::XoChip => XO_CHIP_MEMORY_SIZE,
+            _ => MEMORY_SIZE,
+        };
+        if memory.len() != memory_size {
+            return Err(StateError::Corrupted("memory size mismatch"));
+        }
+        machine.memory = memory.to_vec();
+        if !reader.0.is_empty() {
+            return Err(StateError::Corrupted("trailing data"));
+        }
+
+        machine.history = std::mem::take(&mut self.history);
+        #[cfg(debug_assertions)]
+        {
+            machine.debug_log = std::mem::take(&mut self.debug_log);
+        }
+        *self = machine;
+        Ok(())
+    }
+}
@@ -0,0 +1,275 @@
+use crate::render::Palette;
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::Color;
+use std::fmt;
+use std::str::FromStr;
+
+///
+/// Built-in colour themes of the screen, each with a colour per combination of planes for
+/// XO-CHIP roms.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    /// The colours of the terminal, plane 2 in red and both planes in yellow.
+    #[default]
+    Classic,
+    /// Green phosphor monochrome monitor.
+    Green,
+    /// Amber phosphor monochrome monitor.
+    Amber,
+    /// Dark pixels on the yellowish green of early handheld LCDs.
+    Lcd,
+    /// White on black, planes in saturated colours.
+    HighContrast,
+}
+
+impl Theme {
+    ///
+    /// Returns the colours of the theme.
+    ///
+    pub const fn palette(self) -> Palette {
+        let (background, planes) = match self {
+            Theme::Classic => return Palette::CLASSIC,
+            Theme::Green => (
+                Color::Rgb(0x05, 0x14, 0x05),
+                [
+                    Color::Rgb(0x33, 0xFF, 0x33),
+                    Color::Rgb(0x1A, 0x80, 0x1A),
+                    Color::Rgb(0xB3, 0xFF, 0xB3),
+                ],
+            ),
+            Theme::Amber => (
+                Color::Rgb(0x1A, 0x0F, 0x00),
+                [
+                    Color::Rgb(0xFF, 0xB0, 0x00),
+                    Color::Rgb(0x99, 0x5C, 0x00),
+                    Color::Rgb(0xFF, 0xDD, 0x88),
+                ],
+            ),
+            Theme::Lcd => (
+                Color::Rgb(0x9B, 0xBC, 0x0F),
+                [
+                    Color::Rgb(0x0F, 0x38, 0x0F),
+                    Color::Rgb(0x6B, 0x8C, 0x0F),
+                    Color::Rgb(0x30, 0x62, 0x30),
+                ],
+            ),
+            Theme::HighContrast => (
+                Color::Rgb(0x00, 0x00, 0x00),
+                [
+                    Color::Rgb(0xFF, 0xFF, 0xFF),
+                    Color::Rgb(0xFF, 0x00, 0xFF),
+                    Color::Rgb(0x00, 0xFF, 0xFF),
+                ],
+            ),
+        };
+        Palette { background, planes }
+    }
+}
+
+///
+/// Error returned when parsing an unknown theme name.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownTheme(pub String);
+
+impl fmt::Display for UnknownTheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown theme '{}', expected one of: classic, green, amber, lcd, high-contrast",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownTheme {}
+
+impl FromStr for Theme {
+    type Err = UnknownTheme;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "classic" => Ok(Theme::Classic),
+            "green" => Ok(Theme::Green),
+            "amber" => Ok(Theme::Amber),
+            "lcd" => Ok(Theme::Lcd),
+            "high-contrast" | "highcontrast" => Ok(Theme::HighContrast),
+            _ => Err(UnknownTheme(name.to_string())),
+        }
+    }
+}
+
+///
+/// Colours a terminal can display.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    /// Any RGB colour.
+    TrueColor,
+    /// The 256 colours of the xterm palette.
+    Indexed,
+    /// The 16 ANSI colours.
+    Ansi,
+}
+
+// RGB values of the 16 ANSI colours, as xterm displays them.
+const ANSI_COLORS: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0x00, 0x00, 0x00)),
+    (Color::Red, (0xCD, 0x00, 0x00)),
+    (Color::Green, (0x00, 0xCD, 0x00)),
+    (Color::Yellow, (0xCD, 0xCD, 0x00)),
+    (Color::Blue, (0x00, 0x00, 0xEE)),
+    (Color::Magenta, (0xCD, 0x00, 0xCD)),
+    (Color::Cyan, (0x00, 0xCD, 0xCD)),
+    (Color::Gray, (0xE5, 0xE5, 0xE5)),
+    (Color::DarkGray, (0x7F, 0x7F, 0x7F)),
+    (Color::LightRed, (0xFF, 0x00, 0x00)),
+    (Color::LightGreen, (0x00, 0xFF, 0x00)),
+    (Color::LightYellow, (0xFF, 0xFF, 0x00)),
+    (Color::LightBlue, (0x5C, 0x5C, 0xFF)),
+    (Color::LightMagenta, (0xFF, 0x00, 0xFF)),
+    (Color::LightCyan, (0x00, 0xFF, 0xFF)),
+    (Color::White, (0xFF, 0xFF, 0xFF)),
+];
+
+// Levels of each component in the 6x6x6 colour cube of the xterm palette, from index 16.
+const CUBE_LEVELS: [u8; 6] = [0x00, 0x5F, 0x87, 0xAF, 0xD7, 0xFF];
+
+fn distance((r, g, b): (u8, u8, u8), (to_r, to_g, to_b): (u8, u8, u8)) -> u32 {
+    let square = |from: u8, to: u8| (from as i32 - to as i32).pow(2) as u32;
+    square(r, to_r) + square(g, to_g) + square(b, to_b)
+}
+
+// RGB value of a colour of the xterm palette.
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI_COLORS[index as usize].1,
+        16..=231 => {
+            let cube = index - 16;
+            (
+                CUBE_LEVELS[cube as usize / 36],
+                CUBE_LEVELS[cube as usize / 6 % 6],
+                CUBE_LEVELS[cube as usize % 6],
+            )
+        }
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+// Closest colour of the xterm palette past the 16 ANSI ones, from the cube or the gray ramp.
+fn closest_indexed(rgb: (u8, u8, u8)) -> u8 {
+    let level = |component: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&level| (CUBE_LEVELS[level] as i32 - component as i32).abs())
+            .unwrap_or_default() as u8
+    };
+    let cube = 16 + 36 * level(rgb.0) + 6 * level(rgb.1) + level(rgb.2);
+    let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+    if distance(rgb, indexed_rgb(gray)) < distance(rgb, indexed_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+fn closest_ansi(rgb: (u8, u8, u8)) -> Color {
+    ANSI_COLORS
+        .iter()
+        .min_by_key(|&&(_, ansi)| distance(rgb, ansi))
+        .map_or(Color::Reset, |&(color, _)| color)
+}
+
+impl ColorDepth {
+    ///
+    /// Guesses the colours the terminal displays from the `COLORTERM` and `TERM` environment
+    /// variables.
+    ///
+    pub fn detect() -> Self {
+        let variable = |name| std::env::var(name).ok();
+        Self::from_env(
+            variable("COLORTERM").as_deref(),
+            variable("TERM").as_deref(),
+        )
+    }
+
+    ///
+    /// Guesses the colours a terminal displays from its `COLORTERM` and `TERM` variables:
+    /// terminals announce RGB colours through `COLORTERM`, and 256 colours through their name.
+    ///
+    pub fn from_env(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        let term = term.unwrap_or_default();
+        if matches!(colorterm, Some("truecolor" | "24bit")) || term.ends_with("-direct") {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Indexed
+        } else {
+            ColorDepth::Ansi
+        }
+    }
+
+    ///
+    /// Returns the closest colour the terminal displays.
+    ///
+    pub fn convert(self, color: Color) -> Color {
+        match (self, color) {
+            (ColorDepth::TrueColor, color) => color,
+            (ColorDepth::Indexed, Color::Rgb(r, g, b)) => {
+                Color::Indexed(closest_indexed((r, g, b)))
+            }
+            (ColorDepth::Ansi, Color::Rgb(r, g, b)) => closest_ansi((r, g, b)),
+            (ColorDepth::Ansi, Color::Indexed(index)) => closest_ansi(indexed_rgb(index)),
+            (_, color) => color,
+        }
+    }
+
+    ///
+    /// Converts the colours of the cells of the area to the closest ones the terminal displays.
+    ///
+    pub fn convert_area(self, buffer: &mut Buffer, area: Rect) {
+        let area = area.intersection(buffer.area);
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = &mut buffer[(x, y)];
+                cell.fg = self.convert(cell.fg);
+                cell.bg = self.convert(cell.bg);
+            }
+        }
+    }
+}
+
+///
+/// Error returned when parsing an unknown colour depth.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownColorDepth(pub String);
+
+impl fmt::Display for UnknownColorDepth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown colour depth '{}', expected one of: truecolor, 256, 16",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownColorDepth {}
+
+impl FromStr for ColorDepth {
+    type Err = UnknownColorDepth;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "truecolor" | "24bit" => Ok(ColorDepth::TrueColor),
+            "256" => Ok(ColorDepth::Indexed),
+            "16" => Ok(ColorDepth::Ansi),
+            _ => Err(UnknownColorDepth(name.to_string())),
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use crate::quirks::Quirks;
+use std::fmt;
+use std::str::FromStr;
+
+///
+/// The Chip8 flavour a machine emulates, deciding which instructions are available.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Variant {
+    /// The original instruction set, with a 64x32 screen.
+    #[default]
+    Chip8,
+    /// SUPER-CHIP 1.1: adds a 128x64 high resolution mode, scrolling, 16x16 sprites
+    /// (8x16 in low resolution), a large font and the RPL user flags.
+    SuperChip,
+    /// XO-CHIP: SUPER-CHIP plus 64 KiB of memory, two bitplanes, register ranges
+    /// and a programmable audio pattern.
+    XoChip,
+}
+
+impl Variant {
+    ///
+    /// Returns the quirks roms written for this variant usually expect.
+    ///
+    pub const fn default_quirks(self) -> Quirks {
+        match self {
+            Variant::Chip8 => Quirks::cosmac_vip(),
+            Variant::SuperChip => Quirks::super_chip(),
+            Variant::XoChip => Quirks::xo_chip(),
+        }
+    }
+}
+
+///
+/// Error returned when parsing an unknown variant name.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownVariant(pub String);
+
+impl fmt::Display for UnknownVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown variant '{}', expected one of: chip8, schip, xochip",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownVariant {}
+
+impl FromStr for Variant {
+    type Err = UnknownVariant;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "chip8" | "chip-8" => Ok(Variant::Chip8),
+            "schip" | "superchip" | "super-chip" => Ok(Variant::SuperChip),
+            "xochip" | "xo-chip" => Ok(Variant::XoChip),
+            _ => Err(UnknownVariant(name.to_string())),
+        }
+    }
+}
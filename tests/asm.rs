@@ -0,0 +1,139 @@
+use rusty_chip8::asm::{assemble, AsmError, AsmErrorKind};
+use rusty_chip8::disasm::{mnemonic, Syntax};
+use rusty_chip8::{Chip8, Keypad};
+
+fn error(source: &str) -> AsmError {
+    assemble(source).unwrap_err()
+}
+
+#[test]
+fn labels_resolve_forward_and_backward() {
+    let source = "
+        : main
+            i := sprite   # defined below
+            draw
+            jump main
+        : draw
+            sprite v0 v1 2
+            return
+        : sprite
+            0xFF 0b10000001
+    ";
+    assert_eq!(
+        assemble(source).unwrap(),
+        [0xA2, 0x0A, 0x22, 0x06, 0x12, 0x00, 0xD0, 0x12, 0x00, 0xEE, 0xFF, 0x81]
+    );
+}
+
+#[test]
+fn aliases_and_constants_name_registers_and_values() {
+    let source = "
+        :alias x v3
+        :const SPEED 4
+        x := SPEED
+        x += -1
+        x -= 2
+        :byte SPEED
+    ";
+    assert_eq!(
+        assemble(source).unwrap(),
+        [0x63, 0x04, 0x73, 0xFF, 0x73, 0xFE, 0x04]
+    );
+}
+
+#[test]
+fn disassembled_octo_mnemonics_assemble_back() {
+    for opcode in 0..=0xFFFF {
+        if opcode == 0xF000 {
+            continue;
+        }
+        if let Some(text) = mnemonic(opcode, Syntax::Octo) {
+            let assembled = assemble(&text).unwrap_or_else(|error| panic!("{}: {}", text, error));
+            assert_eq!(assembled, opcode.to_be_bytes(), "{}", text);
+        }
+    }
+    assert_eq!(
+        assemble("i := long 0x1234").unwrap(),
+        [0xF0, 0x00, 0x12, 0x34]
+    );
+}
+
+#[test]
+fn control_flow_blocks_run_as_written() {
+    let source = "
+        v0 := 0
+        loop
+            v0 += 1
+            if v0 == 3 begin
+                v1 := 0xAA
+            else
+                v2 += 1
+            end
+            if v0 != 3 then
+        again
+        : halt
+        jump halt
+    ";
+    let rom = assemble(source).unwrap();
+    let mut vm = Chip8::default();
+    vm.load(&rom).unwrap();
+    for _ in 0..100 {
+        vm.cycle(Keypad::default()).unwrap();
+    }
+    assert_eq!(vm.program_counter as usize, 0x200 + rom.len() - 2);
+    assert_eq!(vm.register[0], 3);
+    assert_eq!(vm.register[1], 0xAA);
+    assert_eq!(vm.register[2], 2);
+}
+
+#[test]
+fn errors_carry_line_and_column() {
+    let unknown = error("clear\n  jump nowhere\n");
+    assert_eq!((unknown.line, unknown.column), (2, 8));
+    assert_eq!(unknown.kind, AsmErrorKind::UnknownName("nowhere".into()));
+    assert_eq!(
+        unknown.to_string(),
+        "line 2, column 8: undefined name 'nowhere'"
+    );
+
+    let range = error("v1 := 0x100");
+    assert_eq!((range.line, range.column), (1, 7));
+    assert_eq!(
+        range.kind,
+        AsmErrorKind::OutOfRange {
+            value: 0x100,
+            max: 0xFF
+        }
+    );
+
+    let scroll = error("scroll-down 0");
+    assert_eq!((scroll.line, scroll.column), (1, 13));
+
+    let register = error("sprite v1 x 3");
+    assert_eq!((register.line, register.column), (1, 11));
+
+    let duplicate = error(": main\n: main");
+    assert_eq!(duplicate.kind, AsmErrorKind::DuplicateName("main".into()));
+
+    assert_eq!(error("i :=").kind, AsmErrorKind::UnexpectedEnd);
+    assert_eq!(
+        error("loop").kind,
+        AsmErrorKind::UnclosedBlock("loop".into())
+    );
+    assert_eq!(
+        error("end").kind,
+        AsmErrorKind::UnmatchedBlock("end".into())
+    );
+
+    // XO-CHIP roms reach past 0xFFF, where loops cannot jump back to.
+    let padding = "0 ".repeat(0x1000 - 0x200);
+    let far_loop = error(&format!("{}\nloop again", padding));
+    assert_eq!((far_loop.line, far_loop.column), (2, 1));
+    assert_eq!(
+        far_loop.kind,
+        AsmErrorKind::OutOfRange {
+            value: 0x1000,
+            max: 0xFFF
+        }
+    );
+}
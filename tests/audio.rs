@@ -0,0 +1,175 @@
+use rusty_chip8::asm::assemble;
+use rusty_chip8::audio::{
+    AudioSettings, AudioSink, Bell, Synthesizer, WavRecorder, Waveform, WAV_SAMPLE_RATE,
+};
+use rusty_chip8::headless::{self, InputTimeline};
+use rusty_chip8::{Chip8, Variant};
+use std::convert::TryInto;
+
+const SAMPLES_PER_FRAME: usize = WAV_SAMPLE_RATE as usize / 60;
+
+// Records the sound of a rom setting the sound timer to `frames`, then looping forever.
+fn record(frames: u8, settings: AudioSettings) -> WavRecorder {
+    let mut vm = Chip8::default();
+    vm.seed(0);
+    let source = format!("v0 := {} buzzer := v0 : halt jump halt", frames);
+    vm.load(&assemble(&source).unwrap()).unwrap();
+    let mut recorder = WavRecorder::new(settings);
+    headless::run(
+        &mut vm,
+        &InputTimeline::default(),
+        70,
+        10,
+        None,
+        &mut recorder,
+    )
+    .unwrap();
+    recorder
+}
+
+fn sign_changes(samples: &[i16]) -> usize {
+    samples
+        .windows(2)
+        .filter(|pair| (pair[0] < 0) != (pair[1] < 0))
+        .count()
+}
+
+#[test]
+fn the_tone_lasts_as_many_frames_as_the_sound_timer() {
+    let recorder = record(3, AudioSettings::default());
+    let samples = recorder.samples();
+    assert_eq!(samples.len(), 70 * SAMPLES_PER_FRAME);
+    let (tone, silence) = samples.split_at(3 * SAMPLES_PER_FRAME);
+    assert!(tone.iter().all(|&sample| sample != 0));
+    assert!(silence.iter().all(|&sample| sample == 0));
+}
+
+#[test]
+fn the_tone_follows_the_frequency_and_volume() {
+    let settings = AudioSettings {
+        frequency: 441.0,
+        volume: 0.5,
+        ..AudioSettings::default()
+    };
+    let recorder = record(60, settings);
+    let second = &recorder.samples()[..WAV_SAMPLE_RATE as usize];
+    // Two sign changes per period.
+    let changes = sign_changes(second);
+    assert!((881..=883).contains(&changes), "{} sign changes", changes);
+    let peak = second.iter().map(|sample| sample.unsigned_abs()).max();
+    assert_eq!(peak, Some(i16::MAX as u16 / 2));
+}
+
+#[test]
+fn loaded_states_resume_the_tone_where_it_was() {
+    let rom = assemble("v0 := 60 buzzer := v0 : halt jump halt").unwrap();
+    let settings = AudioSettings {
+        waveform: Waveform::Sine,
+        ..AudioSettings::default()
+    };
+    let play = |vm: &mut Chip8, recorder: &mut WavRecorder, frames| {
+        headless::run(vm, &InputTimeline::default(), frames, 10, None, recorder).unwrap();
+    };
+    let mut vm = Chip8::default();
+    vm.load(&rom).unwrap();
+    let mut continuous = WavRecorder::new(settings);
+    play(&mut vm, &mut continuous, 10);
+    let state = vm.save_state();
+    play(&mut vm, &mut continuous, 10);
+
+    let mut restored = Chip8::default();
+    restored.load(&rom).unwrap();
+    restored.load_state(&state).unwrap();
+    let mut resumed = WavRecorder::new(settings);
+    play(&mut restored, &mut resumed, 10);
+    let expected = &continuous.samples()[10 * SAMPLES_PER_FRAME..];
+    assert_eq!(resumed.samples().len(), expected.len());
+    for (&sample, &expected) in resumed.samples().iter().zip(expected) {
+        assert!((sample - expected).abs() < 16, "{} != {}", sample, expected);
+    }
+}
+
+#[test]
+fn recordings_export_as_wav() {
+    let recorder = record(1, AudioSettings::default());
+    let wav = recorder.to_wav();
+    let field = |offset: usize| u32::from_le_bytes(wav[offset..offset + 4].try_into().unwrap());
+    let short = |offset: usize| u16::from_le_bytes([wav[offset], wav[offset + 1]]);
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(field(4) as usize, wav.len() - 8);
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!((short(20), short(22)), (1, 1));
+    assert_eq!(field(24), WAV_SAMPLE_RATE);
+    assert_eq!(short(34), 16);
+    assert_eq!(&wav[36..40], b"data");
+    assert_eq!(field(40) as usize, recorder.samples().len() * 2);
+    assert_eq!(short(44) as i16, recorder.samples()[0]);
+}
+
+#[test]
+fn waveforms_parse_and_span_their_period() {
+    let waveforms: Vec<Waveform> = ["square", "Triangle", "saw", "sine"]
+        .iter()
+        .map(|name| name.parse().unwrap())
+        .collect();
+    assert_eq!(
+        waveforms,
+        [
+            Waveform::Square,
+            Waveform::Triangle,
+            Waveform::Sawtooth,
+            Waveform::Sine
+        ]
+    );
+    assert!("noise".parse::<Waveform>().is_err());
+    for (waveform, expected) in [
+        (Waveform::Square, [1.0, 1.0, -1.0, -1.0]),
+        (Waveform::Triangle, [-1.0, 0.0, 1.0, 0.0]),
+        (Waveform::Sawtooth, [-1.0, -0.5, 0.0, 0.5]),
+        (Waveform::Sine, [0.0, 1.0, 0.0, -1.0]),
+    ] {
+        for (quarter, &expected) in expected.iter().enumerate() {
+            let sample = waveform.sample(quarter as f32 / 4.0);
+            assert!(
+                (sample - expected).abs() < 1e-6,
+                "{:?} {}",
+                waveform,
+                quarter
+            );
+        }
+    }
+}
+
+#[test]
+fn xo_chip_patterns_play_at_the_pitch_rate() {
+    let mut vm = Chip8::with_variant(Variant::XoChip, Variant::XoChip.default_quirks());
+    vm.audio_pattern = [0xFF, 0x00].repeat(8).try_into().unwrap();
+    vm.buzzer = true;
+    let mut synthesizer = Synthesizer::new(AudioSettings::default(), 8000);
+    synthesizer.update(&vm);
+    // 4000 bits per second at the default pitch, each bit lasting 2 samples.
+    let mut samples = [0.0; 64];
+    synthesizer.fill(&mut samples);
+    assert!(samples[..16].iter().all(|&sample| sample > 0.0));
+    assert!(samples[16..32].iter().all(|&sample| sample < 0.0));
+
+    // 48 steps up double the rate.
+    vm.pitch = 64 + 48;
+    synthesizer.silence();
+    synthesizer.update(&vm);
+    synthesizer.fill(&mut samples);
+    assert!(samples[..8].iter().all(|&sample| sample > 0.0));
+    assert!(samples[8..16].iter().all(|&sample| sample < 0.0));
+}
+
+#[test]
+fn the_bell_rings_when_the_buzzer_starts() {
+    let mut output = Vec::new();
+    let mut bell = Bell::new(&mut output);
+    let mut vm = Chip8::default();
+    for buzzer in [false, true, true, false, true] {
+        vm.buzzer = buzzer;
+        bell.frame(&vm);
+    }
+    assert_eq!(output, b"\x07\x07");
+}
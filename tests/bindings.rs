@@ -0,0 +1,125 @@
+use crossterm::event::KeyCode;
+use rusty_chip8::bindings::{
+    key_name, parse_key, Action, BindingsError, KeyBindings, KeyboardLayout,
+};
+
+#[test]
+fn qwerty_preset_maps_the_left_side_of_the_keyboard() {
+    let bindings = KeyBindings::preset(KeyboardLayout::Qwerty);
+    assert_eq!(
+        bindings.action(KeyCode::Char('1')),
+        Some(Action::Keypad(0x1))
+    );
+    assert_eq!(
+        bindings.action(KeyCode::Char('4')),
+        Some(Action::Keypad(0xC))
+    );
+    assert_eq!(
+        bindings.action(KeyCode::Char('x')),
+        Some(Action::Keypad(0x0))
+    );
+    assert_eq!(
+        bindings.action(KeyCode::Char('v')),
+        Some(Action::Keypad(0xF))
+    );
+    assert_eq!(bindings.action(KeyCode::Char('p')), Some(Action::Quit));
+    assert_eq!(bindings.action(KeyCode::Char('m')), None);
+}
+
+#[test]
+fn layouts_map_the_same_physical_keys() {
+    let azerty = KeyBindings::preset(KeyboardLayout::Azerty);
+    assert_eq!(azerty.action(KeyCode::Char('a')), Some(Action::Keypad(0x4)));
+    assert_eq!(azerty.action(KeyCode::Char('w')), Some(Action::Keypad(0xA)));
+    assert_eq!(azerty.action(KeyCode::Char('é')), Some(Action::Keypad(0x2)));
+    assert_eq!(azerty.action(KeyCode::Char('2')), Some(Action::Keypad(0x2)));
+
+    let qwertz = KeyBindings::preset(KeyboardLayout::Qwertz);
+    assert_eq!(qwertz.action(KeyCode::Char('y')), Some(Action::Keypad(0xA)));
+
+    let dvorak = KeyBindings::preset(KeyboardLayout::Dvorak);
+    assert_eq!(dvorak.action(KeyCode::Char('p')), Some(Action::Keypad(0xD)));
+    assert_eq!(dvorak.action(KeyCode::Esc), Some(Action::Quit));
+}
+
+#[test]
+fn letters_match_regardless_of_case() {
+    let bindings = KeyBindings::default();
+    assert_eq!(
+        bindings.action(KeyCode::Char('Q')),
+        Some(Action::Keypad(0x4))
+    );
+}
+
+#[test]
+fn bindings_file_extends_a_preset() {
+    let document = r#"
+        layout = "azerty"
+
+        [keypad]
+        2 = ["z", "Up"]
+        8 = ["s", "Down"]
+        "A" = "Enter"
+
+        [actions]
+        quit = "F10"
+    "#;
+    let bindings = KeyBindings::from_toml(document, KeyboardLayout::Qwerty).unwrap();
+    assert_eq!(bindings.action(KeyCode::Up), Some(Action::Keypad(0x2)));
+    assert_eq!(
+        bindings.action(KeyCode::Char('z')),
+        Some(Action::Keypad(0x2))
+    );
+    assert_eq!(bindings.action(KeyCode::Down), Some(Action::Keypad(0x8)));
+    assert_eq!(bindings.action(KeyCode::Enter), Some(Action::Keypad(0xA)));
+    assert_eq!(bindings.keys(Action::Quit), &[KeyCode::F(10)]);
+    // Untouched keys keep the azerty preset.
+    assert_eq!(
+        bindings.action(KeyCode::Char('a')),
+        Some(Action::Keypad(0x4))
+    );
+}
+
+#[test]
+fn invalid_bindings_files_are_rejected() {
+    let qwerty = KeyboardLayout::Qwerty;
+    assert!(KeyBindings::from_toml("layout = \"colemak\"", qwerty).is_err());
+    assert!(KeyBindings::from_toml("[keypad]\nG = \"g\"", qwerty).is_err());
+    assert!(KeyBindings::from_toml("[keypad]\n1 = \"NotAKey\"", qwerty).is_err());
+    assert!(KeyBindings::from_toml("[actions]\nfly = \"f\"", qwerty).is_err());
+}
+
+#[test]
+fn keys_listed_under_two_entries_are_rejected() {
+    let document = "[keypad]\n2 = [\"z\", \"Up\"]\n\n[actions]\nload1 = [\"F5\", \"Up\"]";
+    assert_eq!(
+        KeyBindings::from_toml(document, KeyboardLayout::Qwerty),
+        Err(BindingsError(
+            "key 'Up' is listed under both keypad 2 and actions load1".to_string()
+        ))
+    );
+    let document = "[keypad]\n2 = [\"z\", \"z\"]";
+    assert!(KeyBindings::from_toml(document, KeyboardLayout::Qwerty).is_ok());
+}
+
+#[test]
+fn key_names_round_trip() {
+    for name in ["a", "Up", "Esc", "F5", "Space", "PageDown"] {
+        let code = parse_key(name).unwrap();
+        assert_eq!(parse_key(&key_name(code)), Some(code));
+    }
+    assert_eq!(parse_key("F13"), None);
+}
+
+#[test]
+fn save_state_slots_have_hotkeys() {
+    let bindings = KeyBindings::default();
+    assert_eq!(bindings.action(KeyCode::F(1)), Some(Action::SaveState(1)));
+    assert_eq!(bindings.action(KeyCode::F(8)), Some(Action::LoadState(4)));
+
+    let document = "[actions]\nsave2 = \"F12\"\nload2 = \"Home\"";
+    let bindings = KeyBindings::from_toml(document, KeyboardLayout::Qwerty).unwrap();
+    assert_eq!(bindings.keys(Action::SaveState(2)), &[KeyCode::F(12)]);
+    assert_eq!(bindings.action(KeyCode::Home), Some(Action::LoadState(2)));
+    assert_eq!(Action::SaveState(3).name(), Some("save3"));
+}
@@ -0,0 +1,140 @@
+use rusty_chip8::asm::assemble;
+use rusty_chip8::audio::Mute;
+use rusty_chip8::headless::{self, InputTimeline};
+use rusty_chip8::{Chip8, Quirks, Variant};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Set to write the golden images of the roms lacking one, or that do not match anymore.
+const UPDATE_GOLDEN: &str = "UPDATE_GOLDEN";
+
+#[derive(Deserialize)]
+struct Manifest {
+    rom: Vec<Rom>,
+}
+
+#[derive(Deserialize)]
+struct Rom {
+    name: String,
+    file: String,
+    frames: usize,
+    #[serde(default = "default_variant")]
+    variant: String,
+    quirks: Option<String>,
+    #[serde(default = "default_instructions_per_frame")]
+    ipf: usize,
+    #[serde(default)]
+    input: String,
+}
+
+fn default_variant() -> String {
+    "chip8".to_string()
+}
+
+fn default_instructions_per_frame() -> usize {
+    rusty_chip8::scheduler::DEFAULT_INSTRUCTIONS_PER_FRAME
+}
+
+fn roms_directory() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("roms")
+}
+
+// Runs the rom and returns its final screen, or `None` when the rom is not there.
+fn run(rom: &Rom) -> Option<String> {
+    let path = roms_directory().join(&rom.file);
+    let bytes = if rom.file.ends_with(".8o") {
+        let source = fs::read_to_string(&path).ok()?;
+        assemble(&source).unwrap_or_else(|error| panic!("{}: {}", rom.file, error))
+    } else {
+        fs::read(&path).ok()?
+    };
+    let variant: Variant = rom.variant.parse().unwrap();
+    let quirks: Quirks = rom
+        .quirks
+        .as_ref()
+        .map_or(variant.default_quirks(), |quirks| quirks.parse().unwrap());
+    let timeline: InputTimeline = rom.input.parse().unwrap();
+    let mut vm = Chip8::with_variant(variant, quirks);
+    vm.seed(0);
+    vm.load(&bytes).unwrap();
+    headless::run(&mut vm, &timeline, rom.frames, rom.ipf, None, &mut Mute)
+        .unwrap_or_else(|error| panic!("{}: {}", rom.name, error));
+    Some(headless::ascii(&vm))
+}
+
+// Expected and actual screens next to each other, rows that differ being marked.
+fn side_by_side(expected: &str, actual: &str) -> String {
+    let width = expected.lines().map(str::len).max().unwrap_or_default();
+    let mut diff = format!("{:<width$}     {}\n", "expected", "actual", width = width);
+    let (mut expected, mut actual) = (expected.lines(), actual.lines());
+    loop {
+        let (left, right) = match (expected.next(), actual.next()) {
+            (None, None) => break,
+            (left, right) => (left.unwrap_or_default(), right.unwrap_or_default()),
+        };
+        let marker = if left == right { ' ' } else { '!' };
+        diff.push_str(&format!(
+            "{:<width$}  {}  {}\n",
+            left,
+            marker,
+            right,
+            width = width
+        ));
+    }
+    diff
+}
+
+#[test]
+fn roms_match_their_golden_images() {
+    let update = std::env::var_os(UPDATE_GOLDEN).is_some();
+    let manifest: Manifest =
+        toml::from_str(&fs::read_to_string(roms_directory().join("manifest.toml")).unwrap())
+            .unwrap();
+    let mut failures = Vec::new();
+    for rom in &manifest.rom {
+        let actual = match run(rom) {
+            Some(screen) => screen,
+            None => {
+                eprintln!("{}: {} not found in tests/roms", rom.name, rom.file);
+                failures.push(rom.name.as_str());
+                continue;
+            }
+        };
+        let golden = roms_directory()
+            .join("golden")
+            .join(format!("{}.txt", rom.name));
+        match fs::read_to_string(&golden) {
+            Ok(expected) if expected == actual => {}
+            _ if update => fs::write(&golden, &actual).unwrap(),
+            Ok(expected) => {
+                eprintln!(
+                    "{} differs:\n{}",
+                    rom.name,
+                    side_by_side(&expected, &actual)
+                );
+                failures.push(rom.name.as_str());
+            }
+            Err(_) => {
+                eprintln!(
+                    "{} has no golden image, run with {}=1 to write it:\n{}",
+                    rom.name, UPDATE_GOLDEN, actual
+                );
+                failures.push(rom.name.as_str());
+            }
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "roms missing or differing: {}",
+        failures.join(", ")
+    );
+}
+
+#[test]
+fn diffs_mark_the_rows_that_differ() {
+    let diff = side_by_side("..#\n...\n", "..#\n#..\n");
+    assert_eq!(diff, "expected     actual\n..#     ..#\n...  !  #..\n");
+}
@@ -0,0 +1,78 @@
+use rusty_chip8::debugger::{Debugger, Execution};
+use rusty_chip8::{Chip8, Keypad};
+
+// 0x200 6001: V0 = 1, 0x202 2208: call 0x208, 0x204 7002: V0 += 2, 0x206 1206: loop,
+// 0x208 7110: V1 += 0x10, 0x20A 00EE: return.
+const ROM: [u8; 12] = [
+    0x60, 0x01, 0x22, 0x08, 0x70, 0x02, 0x12, 0x06, 0x71, 0x10, 0x00, 0xEE,
+];
+
+fn load() -> Chip8 {
+    let mut vm = Chip8::default();
+    vm.load(&ROM).unwrap();
+    vm
+}
+
+#[test]
+fn breakpoints_pause_before_the_instruction_runs() {
+    let mut vm = load();
+    let mut debugger = Debugger::default();
+    debugger.toggle_breakpoint(0x208);
+    assert!(debugger.run_frame(&mut vm, Keypad::default(), 10).unwrap());
+    assert!(debugger.is_paused());
+    assert_eq!(vm.program_counter, 0x208);
+    assert_eq!(vm.register[1], 0);
+
+    // Paused machines do not run, timers included.
+    vm.delay_timer = 5;
+    assert!(!debugger.run_frame(&mut vm, Keypad::default(), 10).unwrap());
+    assert_eq!(vm.delay_timer, 5);
+
+    debugger.resume();
+    debugger.run_frame(&mut vm, Keypad::default(), 10).unwrap();
+    assert_eq!(vm.register[1], 0x10);
+    assert_eq!(debugger.execution(), Execution::Running);
+    assert!(!debugger.toggle_breakpoint(0x208));
+    assert!(debugger.breakpoints.is_empty());
+}
+
+#[test]
+fn stepping_runs_one_instruction() {
+    let mut vm = load();
+    let mut debugger = Debugger::default();
+    debugger.step(&mut vm, Keypad::default()).unwrap();
+    debugger.step(&mut vm, Keypad::default()).unwrap();
+    assert!(debugger.is_paused());
+    assert_eq!(vm.program_counter, 0x208);
+    assert_eq!(vm.stack, vec![0x202]);
+}
+
+#[test]
+fn stepping_over_a_call_runs_until_it_returns() {
+    let mut vm = load();
+    let mut debugger = Debugger::default();
+    debugger.step(&mut vm, Keypad::default()).unwrap();
+    debugger.step_over(&mut vm, Keypad::default()).unwrap();
+    assert!(matches!(debugger.execution(), Execution::StepOver { .. }));
+    debugger.run_frame(&mut vm, Keypad::default(), 10).unwrap();
+    assert!(debugger.is_paused());
+    assert_eq!(vm.program_counter, 0x204);
+    assert_eq!(vm.register[1], 0x10);
+    assert_eq!(vm.register[0], 1);
+}
+
+#[test]
+fn stepping_out_runs_until_the_subroutine_returns() {
+    let mut vm = load();
+    let mut debugger = Debugger::default();
+    debugger.step_out(&vm);
+    assert_eq!(debugger.execution(), Execution::Running);
+
+    debugger.step(&mut vm, Keypad::default()).unwrap();
+    debugger.step(&mut vm, Keypad::default()).unwrap();
+    debugger.step_out(&vm);
+    debugger.run_frame(&mut vm, Keypad::default(), 10).unwrap();
+    assert!(debugger.is_paused());
+    assert_eq!(vm.program_counter, 0x204);
+    assert!(vm.stack.is_empty());
+}
@@ -0,0 +1,109 @@
+use rusty_chip8::disasm::{disassemble, listing, mnemonic, Syntax, Target};
+
+// 0x200 2208: call 0x208, 0x202 1206: jump 0x206, 0x204 data 0xFF 0x81,
+// 0x206 1206: loop, 0x208 A204: I = 0x204, 0x20A 00EE: return, 0x20C data 0x3C.
+const ROM: [u8; 13] = [
+    0x22, 0x08, 0x12, 0x06, 0xFF, 0x81, 0x12, 0x06, 0xA2, 0x04, 0x00, 0xEE, 0x3C,
+];
+
+#[test]
+fn opcodes_have_classic_mnemonics() {
+    let classic = |opcode| mnemonic(opcode, Syntax::Classic);
+    assert_eq!(classic(0x6120).as_deref(), Some("LD V1, 0x20"));
+    assert_eq!(classic(0x2208).as_deref(), Some("CALL 0x208"));
+    assert_eq!(classic(0xD01F).as_deref(), Some("DRW V0, V1, 15"));
+    assert_eq!(classic(0xFA65).as_deref(), Some("LD VA, [I]"));
+    assert_eq!(classic(0x5121), None);
+}
+
+#[test]
+fn opcodes_have_octo_mnemonics() {
+    let octo = |opcode| mnemonic(opcode, Syntax::Octo);
+    assert_eq!(octo(0x6120).as_deref(), Some("v1 := 0x20"));
+    assert_eq!(octo(0x2208).as_deref(), Some(":call 0x208"));
+    assert_eq!(octo(0x3A05).as_deref(), Some("if va != 0x05 then"));
+    assert_eq!(octo(0xE19E).as_deref(), Some("if v1 -key then"));
+    assert_eq!(octo(0x8127).as_deref(), Some("v1 =- v2"));
+    assert_eq!(octo(0xF229).as_deref(), Some("i := hex v2"));
+    assert_eq!(octo(0x5121), None);
+}
+
+#[test]
+fn jumps_and_calls_tell_code_from_data() {
+    let entries = disassemble(&ROM, Syntax::Classic);
+    let lines: Vec<(u16, bool, &str)> = entries
+        .iter()
+        .map(|entry| (entry.address, entry.is_code, entry.text.as_str()))
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            (0x200, true, "CALL 0x208"),
+            (0x202, true, "JP 0x206"),
+            (0x204, false, "DB 0xFF, 0x81"),
+            (0x206, true, "JP 0x206"),
+            (0x208, true, "LD I, 0x204"),
+            (0x20A, true, "RET"),
+            (0x20C, false, "DB 0x3C"),
+        ]
+    );
+    assert_eq!(entries[0].target, Some(Target::Entry));
+    assert_eq!(entries[3].target, Some(Target::Jump));
+    assert_eq!(entries[4].target, Some(Target::Subroutine));
+}
+
+#[test]
+fn skips_reach_both_following_instructions() {
+    // 0x200 3000: skip if V0 == 0, 0x202 1206: jump 0x206, 0x204 00FD: exit,
+    // 0x206 00FD: exit, 0x208 data.
+    let rom = [0x30, 0x00, 0x12, 0x06, 0x00, 0xFD, 0x00, 0xFD, 0x12, 0x34];
+    let code: Vec<bool> = disassemble(&rom, Syntax::Classic)
+        .iter()
+        .map(|entry| entry.is_code)
+        .collect();
+    assert_eq!(code, [true, true, true, true, false]);
+}
+
+#[test]
+fn long_loads_span_four_bytes() {
+    let rom = [0xF0, 0x00, 0x12, 0x34, 0x00, 0xFD];
+    let entries = disassemble(&rom, Syntax::Octo);
+    assert_eq!(entries[0].bytes, [0xF0, 0x00, 0x12, 0x34]);
+    assert_eq!(entries[0].text, "i := long 0x1234");
+    assert_eq!(entries[1].address, 0x204);
+    assert_eq!(entries[1].text, "exit");
+}
+
+#[test]
+fn jumps_into_an_instruction_are_listed_as_overlaps() {
+    // 0x200 6012: V0 = 0x12, 0x202 1201: jump into the middle of it, 0x201 1212: jump 0x212.
+    let rom = [0x60, 0x12, 0x12, 0x01];
+    let entries = disassemble(&rom, Syntax::Classic);
+    let lines: Vec<(u16, &str, Option<u16>)> = entries
+        .iter()
+        .map(|entry| (entry.address, entry.text.as_str(), entry.overlaps))
+        .collect();
+    assert_eq!(
+        lines,
+        [
+            (0x200, "LD V0, 0x12", None),
+            (0x201, "JP 0x212", Some(0x200)),
+            (0x202, "JP 0x201", None),
+        ]
+    );
+    assert_eq!(entries[1].bytes, [0x12, 0x12]);
+    let classic = listing(&entries, Syntax::Classic);
+    let overlap = classic.lines().nth(1).unwrap();
+    assert!(overlap.starts_with("0201  12 12        JP 0x212"));
+    assert!(overlap.ends_with("; jump target, overlaps 0200"));
+}
+
+#[test]
+fn listings_have_address_bytes_and_mnemonic_columns() {
+    let octo = listing(&disassemble(&ROM, Syntax::Octo), Syntax::Octo);
+    let lines: Vec<&str> = octo.lines().collect();
+    assert!(lines[0].starts_with("0200  22 08        :call 0x208"));
+    assert!(lines[0].ends_with("# entry point"));
+    assert_eq!(lines[2], "0204  FF 81        0xFF 0x81");
+    assert!(lines[4].ends_with("# subroutine"));
+}
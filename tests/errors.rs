@@ -0,0 +1,128 @@
+use rusty_chip8::{Chip8, EmulationError, Keypad, Quirks, Variant, MEMORY_SIZE};
+
+fn load(rom: &[u8]) -> Chip8 {
+    let mut vm = Chip8::default();
+    vm.load(rom).unwrap();
+    vm
+}
+
+#[test]
+fn unknown_opcodes_report_their_address() {
+    let mut vm = load(&[0x00, 0xE0, 0xFF, 0xFF]);
+    vm.cycle(Keypad::default()).unwrap();
+    assert_eq!(
+        vm.cycle(Keypad::default()),
+        Err(EmulationError::UnknownOpcode {
+            pc: 0x202,
+            opcode: 0xFFFF
+        })
+    );
+    assert_eq!(vm.program_counter, 0x202);
+}
+
+#[test]
+fn returning_with_an_empty_stack_underflows() {
+    let mut vm = load(&[0x00, 0xEE]);
+    assert_eq!(
+        vm.cycle(Keypad::default()),
+        Err(EmulationError::StackUnderflow {
+            pc: 0x200,
+            opcode: 0x00EE
+        })
+    );
+}
+
+#[test]
+fn calls_past_the_stack_limit_overflow() {
+    // 2200: calls itself forever.
+    let mut vm = load(&[0x22, 0x00]);
+    for _ in 0..16 {
+        vm.cycle(Keypad::default()).unwrap();
+    }
+    assert_eq!(
+        vm.cycle(Keypad::default()),
+        Err(EmulationError::StackOverflow {
+            pc: 0x200,
+            opcode: 0x2200,
+            limit: 16
+        })
+    );
+    assert_eq!(vm.stack.len(), 16);
+
+    let mut vm = load(&[0x22, 0x00]);
+    vm.stack_limit = 2;
+    vm.cycle(Keypad::default()).unwrap();
+    vm.cycle(Keypad::default()).unwrap();
+    assert!(vm.cycle(Keypad::default()).is_err());
+}
+
+#[test]
+fn memory_accesses_past_the_end_fail_without_side_effects() {
+    // AFFF: I = 0xFFF, F233: BCD of V2 into I..I+3.
+    let mut vm = load(&[0xAF, 0xFF, 0xF2, 0x33]);
+    vm.cycle(Keypad::default()).unwrap();
+    assert_eq!(
+        vm.cycle(Keypad::default()),
+        Err(EmulationError::MemoryOutOfBounds {
+            address: MEMORY_SIZE,
+            pc: 0x202,
+            opcode: 0xF233
+        })
+    );
+    assert_eq!(vm.memory[0xFFF], 0);
+
+    // AFFE: I = 0xFFE, FF55: store V0 to VF.
+    let mut vm = load(&[0xAF, 0xFE, 0xFF, 0x55]);
+    vm.cycle(Keypad::default()).unwrap();
+    assert!(matches!(
+        vm.cycle(Keypad::default()),
+        Err(EmulationError::MemoryOutOfBounds { .. })
+    ));
+
+    // AFFC: I = 0xFFC, D005: draw 5 rows.
+    let mut vm = load(&[0xAF, 0xFC, 0xD0, 0x05]);
+    vm.cycle(Keypad::default()).unwrap();
+    assert!(vm.cycle(Keypad::default()).is_err());
+    assert!(vm.framebuffer().iter().all(|&pixel| pixel == 0));
+}
+
+#[test]
+fn running_off_the_end_of_memory_fails() {
+    // 1FFE: jump to the last word, which is 0000.
+    let mut vm = load(&[0x1F, 0xFE]);
+    vm.memory[0xFFE..].copy_from_slice(&[0x00, 0xE0]);
+    vm.cycle(Keypad::default()).unwrap();
+    vm.cycle(Keypad::default()).unwrap();
+    assert_eq!(
+        vm.cycle(Keypad::default()),
+        Err(EmulationError::PcOutOfRange {
+            pc: 0x1000,
+            opcode: 0x00E0
+        })
+    );
+}
+
+#[test]
+fn roms_larger_than_memory_are_rejected() {
+    let mut vm = Chip8::default();
+    assert_eq!(vm.load(&[0; MEMORY_SIZE - 0x200]), Ok(()));
+    assert_eq!(
+        vm.load(&[0; MEMORY_SIZE - 0x1FF]),
+        Err(EmulationError::RomTooLarge {
+            size: MEMORY_SIZE - 0x1FF,
+            max: MEMORY_SIZE - 0x200
+        })
+    );
+
+    let mut vm = Chip8::with_variant(Variant::XoChip, Quirks::xo_chip());
+    assert_eq!(vm.load(&[0; MEMORY_SIZE]), Ok(()));
+}
+
+#[test]
+fn errors_describe_the_faulty_instruction() {
+    let error = EmulationError::UnknownOpcode {
+        pc: 0x202,
+        opcode: 0xFFFF,
+    };
+    assert_eq!(error.to_string(), "unknown opcode 0xFFFF at 0x0202");
+}
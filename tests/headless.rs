@@ -0,0 +1,118 @@
+use rusty_chip8::asm::assemble;
+use rusty_chip8::audio::Mute;
+use rusty_chip8::headless::{self, InputTimeline, TimelineError};
+use rusty_chip8::{Chip8, EmulationError};
+use std::process::Command;
+
+// Draws the digit of the key held down at the top left corner, once a key is held.
+const SHOW_KEY: &str = "
+    : main
+        v0 := key
+        i := hex v0
+        sprite v1 v1 5
+    : halt
+        jump halt
+";
+
+fn machine(source: &str) -> Chip8 {
+    let mut vm = Chip8::default();
+    vm.seed(0);
+    vm.load(&assemble(source).unwrap()).unwrap();
+    vm
+}
+
+#[test]
+fn timelines_hold_keys_from_their_frame_on() {
+    let timeline: InputTimeline = "# frame keys\n10 1\n12 -\n".parse().unwrap();
+    assert_eq!(timeline.keypad(9).bits(), 0);
+    assert_eq!(timeline.keypad(11).bits(), 0b10);
+    assert_eq!(timeline.keypad(500).bits(), 0);
+
+    let unordered = "10 1\n5 2".parse::<InputTimeline>().unwrap_err();
+    assert_eq!(unordered.line, 2);
+    let invalid = "\n\n3 g".parse::<InputTimeline>();
+    assert_eq!(
+        invalid,
+        Err(TimelineError {
+            line: 3,
+            reason: "invalid key".into()
+        })
+    );
+}
+
+#[test]
+fn runs_follow_the_timeline_until_the_address() {
+    let mut vm = machine(SHOW_KEY);
+    let timeline = "10 1\n12 -".parse().unwrap();
+    let frames = headless::run(&mut vm, &timeline, 100, 10, Some(0x206), &mut Mute).unwrap();
+    // Key 1 is released during frame 12, FX0A completes then.
+    assert_eq!(frames, 13);
+    assert_eq!(vm.program_counter, 0x206);
+    let ascii = headless::ascii(&vm);
+    let rows: Vec<&str> = ascii.lines().collect();
+    assert_eq!(rows.len(), 32);
+    assert_eq!(&rows[0][..8], "..#.....");
+    assert_eq!(&rows[1][..8], ".##.....");
+}
+
+#[test]
+fn screens_export_as_pbm_and_png() {
+    let mut vm = machine(SHOW_KEY);
+    headless::run(
+        &mut vm,
+        &"0 1\n1 -".parse().unwrap(),
+        5,
+        10,
+        None,
+        &mut Mute,
+    )
+    .unwrap();
+
+    let pbm = headless::pbm(&vm);
+    let header = b"P4\n64 32\n";
+    assert_eq!(&pbm[..header.len()], header);
+    assert_eq!(pbm.len(), header.len() + 64 / 8 * 32);
+    assert_eq!(pbm[header.len()], 0b0010_0000);
+
+    let mut png = Vec::new();
+    headless::write_png(&vm, &mut png).unwrap();
+    let mut reader = png::Decoder::new(&png[..]).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut pixels).unwrap();
+    assert_eq!((reader.info().width, reader.info().height), (64, 32));
+    assert_eq!(pixels, vm.framebuffer());
+}
+
+#[test]
+fn emulation_errors_end_the_run() {
+    let mut vm = machine(":byte 0xFF :byte 0xFF");
+    let error = headless::run(&mut vm, &InputTimeline::default(), 10, 10, None, &mut Mute);
+    assert_eq!(
+        error,
+        Err(EmulationError::UnknownOpcode {
+            pc: 0x200,
+            opcode: 0xFFFF
+        })
+    );
+}
+
+#[test]
+fn headless_binary_exits_with_an_error_code() {
+    let directory = std::env::temp_dir().join(format!("rusty-chip8-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let run = |rom: &[u8]| {
+        let path = directory.join("rom.ch8");
+        std::fs::write(&path, rom).unwrap();
+        Command::new(env!("CARGO_BIN_EXE_rusty-chip8"))
+            .arg(&path)
+            .args(["--headless", "--frames", "3", "--ascii", "-"])
+            .output()
+            .unwrap()
+    };
+    let halted = run(&[0x12, 0x00]);
+    assert!(halted.status.success());
+    assert_eq!(halted.stdout.len(), 65 * 32);
+    let failed = run(&[0xFF, 0xFF]);
+    assert_eq!(failed.status.code(), Some(1));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,61 @@
+use rusty_chip8::instruction::{decode, encode};
+use rusty_chip8::{EmulationError, Instruction, Variant};
+
+#[test]
+fn every_decoded_word_encodes_back() {
+    let mut decoded = 0;
+    for opcode in 0..=0xFFFF {
+        match decode(opcode) {
+            Ok(instruction) => {
+                assert_eq!(encode(&instruction), opcode, "{:?}", instruction);
+                decoded += 1;
+            }
+            Err(error) => assert_eq!(error, EmulationError::UnknownOpcode { pc: 0, opcode }),
+        }
+    }
+    // 38 0NNN words, 10 full NNN ranges, 3 5XYN, 9 8XYN, 9XY0, 2 EXNN, F000, F002 and 14 FXNN.
+    assert_eq!(
+        decoded,
+        38 + 10 * 4096 + 3 * 256 + 9 * 256 + 256 + 2 * 16 + 2 + 14 * 16
+    );
+}
+
+#[test]
+fn opcodes_decode_into_their_operands() {
+    assert_eq!(
+        decode(0xD12F),
+        Ok(Instruction::Draw {
+            x: 1,
+            y: 2,
+            height: 15
+        })
+    );
+    assert_eq!(
+        decode(0xB3A0),
+        Ok(Instruction::JumpWithOffset { address: 0x3A0 })
+    );
+    assert_eq!(decode(0x00C3), Ok(Instruction::ScrollDown { n: 3 }));
+    // SUPER-CHIP only scrolls down by 1 to 15 lines.
+    assert_eq!(
+        decode(0x00C0),
+        Err(EmulationError::UnknownOpcode {
+            pc: 0,
+            opcode: 0x00C0
+        })
+    );
+    assert_eq!(decode(0xF000), Ok(Instruction::SetIndexLong));
+    assert_eq!(decode(0xF201), Ok(Instruction::SelectPlanes { planes: 2 }));
+    assert_eq!(Instruction::SetIndexLong.size(), 4);
+    assert_eq!(Instruction::ClearScreen.size(), 2);
+}
+
+#[test]
+fn instructions_belong_to_their_variants() {
+    let scroll_up = Instruction::ScrollUp { n: 1 };
+    let exit = Instruction::Exit;
+    assert!(!exit.is_supported_by(Variant::Chip8));
+    assert!(exit.is_supported_by(Variant::SuperChip));
+    assert!(!scroll_up.is_supported_by(Variant::SuperChip));
+    assert!(scroll_up.is_supported_by(Variant::XoChip));
+    assert!(Instruction::ClearScreen.is_supported_by(Variant::Chip8));
+}
@@ -0,0 +1,94 @@
+use crossterm::event::KeyCode;
+use rusty_chip8::bindings::Action;
+use rusty_chip8::keypad::HeldKeys;
+use rusty_chip8::{Chip8, Keypad, KeypadEvent};
+use std::time::{Duration, Instant};
+
+fn keys(pressed: &[u8]) -> Keypad {
+    let mut keypad = Keypad::default();
+    pressed.iter().for_each(|&key| keypad.press(key));
+    keypad
+}
+
+#[test]
+fn several_keys_can_be_held_at_once() {
+    let mut keypad = keys(&[0x2, 0xF]);
+    assert!(keypad.is_pressed(0x2) && keypad.is_pressed(0xF));
+    assert_eq!(keypad.bits(), 0x8004);
+    keypad.release(0x2);
+    assert_eq!(keypad.pressed().collect::<Vec<_>>(), vec![0xF]);
+}
+
+#[test]
+fn events_update_the_keypad_state() {
+    let mut keypad = Keypad::default();
+    assert!(KeypadEvent::Press(0x4).apply(&mut keypad));
+    assert!(KeypadEvent::Press(0x6).apply(&mut keypad));
+    assert!(KeypadEvent::Release(0x4).apply(&mut keypad));
+    assert_eq!(keypad, keys(&[0x6]));
+    assert!(!KeypadEvent::Quit.apply(&mut keypad));
+    keypad.release_all();
+    assert_eq!(keypad, Keypad::default());
+}
+
+#[test]
+fn skip_instructions_check_each_held_key() {
+    // 6105: V1 = 5, 6206: V2 = 6, E19E: skip if key V1, 0000: skipped, E2A1: skip if not key V2.
+    let mut vm = Chip8::default();
+    vm.load(&[0x61, 0x05, 0x62, 0x06, 0xE1, 0x9E, 0x00, 0x00, 0xE2, 0xA1])
+        .unwrap();
+    let held = keys(&[0x5, 0x6]);
+    for _ in 0..4 {
+        vm.cycle(held).unwrap();
+    }
+    assert_eq!(vm.program_counter, 0x20A);
+}
+
+#[test]
+fn skip_if_not_pressed_skips_when_no_key_is_held() {
+    // E0A1: skip if key V0 is not pressed.
+    let mut vm = Chip8::default();
+    vm.load(&[0xE0, 0xA1]).unwrap();
+    vm.cycle(Keypad::default()).unwrap();
+    assert_eq!(vm.program_counter, 0x204);
+}
+
+#[test]
+fn wait_for_key_blocks_until_press_and_release() {
+    // F30A: V3 = next key.
+    let mut vm = Chip8::default();
+    vm.load(&[0xF3, 0x0A]).unwrap();
+    vm.cycle(Keypad::default()).unwrap();
+    assert_eq!(vm.program_counter, 0x200);
+    vm.cycle(keys(&[0xB])).unwrap();
+    vm.cycle(keys(&[0xB])).unwrap();
+    assert_eq!(vm.program_counter, 0x200);
+    vm.cycle(Keypad::default()).unwrap();
+    assert_eq!(vm.program_counter, 0x202);
+    assert_eq!(vm.register[3], 0xB);
+}
+
+#[test]
+fn keys_bound_to_the_same_action_are_released_separately() {
+    let (w, up, backspace) = (KeyCode::Char('w'), KeyCode::Up, KeyCode::Backspace);
+    let start = Instant::now();
+    let at = |millis| Some(start + Duration::from_millis(millis));
+    let mut held = HeldKeys::default();
+    assert!(!held.press(w, Action::Keypad(5), at(100)));
+    assert!(!held.press(backspace, Action::Rewind, at(150)));
+    assert!(held.press(up, Action::Keypad(5), at(300)));
+    assert_eq!(held.next_deadline(), at(100));
+
+    // Each key keeps its own deadline, key 5 staying down while the up arrow is.
+    assert_eq!(
+        held.expire(start + Duration::from_millis(200)),
+        [Action::Rewind]
+    );
+    assert_eq!(held.next_deadline(), at(300));
+    assert_eq!(held.release(w), None);
+    assert_eq!(
+        held.expire(start + Duration::from_millis(300)),
+        [Action::Keypad(5)]
+    );
+    assert_eq!(held.next_deadline(), None);
+}
@@ -0,0 +1,153 @@
+use crossterm::event::KeyCode;
+use rusty_chip8::memory_view::{self, MemoryView, Mode, Outcome, FLASH_FRAMES};
+use rusty_chip8::Chip8;
+
+// Types the keys into the view, returning what became of each.
+fn type_keys(
+    view: &mut MemoryView,
+    vm: &mut Chip8,
+    keys: &[KeyCode],
+    editable: bool,
+) -> Vec<Outcome> {
+    keys.iter()
+        .map(|&key| view.input(key, vm, editable))
+        .collect()
+}
+
+#[test]
+fn written_bytes_flash_for_a_while() {
+    let mut vm = Chip8::default();
+    let mut view = MemoryView::default();
+    view.refresh(&vm.memory);
+    assert!(!view.is_flashing(0x300));
+    vm.memory[0x300] = 0x42;
+    view.refresh(&vm.memory);
+    assert!(view.is_flashing(0x300));
+    assert!(!view.is_flashing(0x301));
+    for _ in 1..FLASH_FRAMES {
+        view.refresh(&vm.memory);
+    }
+    assert!(view.is_flashing(0x300));
+    view.refresh(&vm.memory);
+    assert!(!view.is_flashing(0x300));
+}
+
+#[test]
+fn the_cursor_stays_within_memory() {
+    let mut vm = Chip8::default();
+    let mut view = MemoryView::default();
+    view.input(KeyCode::Home, &mut vm, false);
+    type_keys(&mut view, &mut vm, &[KeyCode::Left, KeyCode::Up], false);
+    assert_eq!(view.cursor, 0);
+    type_keys(&mut view, &mut vm, &[KeyCode::Down, KeyCode::Right], false);
+    assert_eq!(view.cursor, 0x11);
+    view.input(KeyCode::PageDown, &mut vm, false);
+    assert_eq!(view.cursor, 0x11 + 16 * 16);
+    view.input(KeyCode::End, &mut vm, false);
+    type_keys(&mut view, &mut vm, &[KeyCode::Right, KeyCode::Down], false);
+    assert_eq!(view.cursor, vm.memory.len() - 1);
+}
+
+#[test]
+fn go_to_moves_the_cursor_to_the_typed_address() {
+    let mut vm = Chip8::default();
+    let mut view = MemoryView::default();
+    let keys = [
+        KeyCode::Char('g'),
+        KeyCode::Char('1'),
+        KeyCode::Char('2'),
+        KeyCode::Backspace,
+        KeyCode::Char('A'),
+        KeyCode::Char('0'),
+    ];
+    type_keys(&mut view, &mut vm, &keys, true);
+    assert_eq!(view.mode, Mode::GoTo("1A0".into()));
+    view.input(KeyCode::Enter, &mut vm, true);
+    assert_eq!((view.cursor, &view.mode), (0x1A0, &Mode::Browse));
+
+    // Addresses past the memory go to its last byte, Esc cancels.
+    type_keys(
+        &mut view,
+        &mut vm,
+        &[KeyCode::Char('g'), KeyCode::Char('f')],
+        true,
+    );
+    type_keys(
+        &mut view,
+        &mut vm,
+        &[KeyCode::Char('f'), KeyCode::Char('f')],
+        true,
+    );
+    view.input(KeyCode::Enter, &mut vm, true);
+    assert_eq!(view.cursor, vm.memory.len() - 1);
+    type_keys(
+        &mut view,
+        &mut vm,
+        &[KeyCode::Char('g'), KeyCode::Char('0')],
+        true,
+    );
+    view.input(KeyCode::Esc, &mut vm, true);
+    assert_eq!(
+        (view.cursor, &view.mode),
+        (vm.memory.len() - 1, &Mode::Browse)
+    );
+}
+
+#[test]
+fn bytes_are_only_edited_when_allowed() {
+    let mut vm = Chip8::default();
+    let mut view = MemoryView::default();
+    let digits = [KeyCode::Char('c'), KeyCode::Char('3')];
+    assert_eq!(
+        type_keys(&mut view, &mut vm, &digits, false),
+        [Outcome::ReadOnly, Outcome::ReadOnly]
+    );
+    assert_eq!((vm.memory[0x200], view.cursor), (0, 0x200));
+
+    type_keys(&mut view, &mut vm, &digits, true);
+    assert_eq!((vm.memory[0x200], view.cursor), (0xC3, 0x201));
+
+    // Moving or Esc drop the half typed byte.
+    type_keys(
+        &mut view,
+        &mut vm,
+        &[KeyCode::Char('1'), KeyCode::Esc],
+        true,
+    );
+    type_keys(
+        &mut view,
+        &mut vm,
+        &[KeyCode::Char('2'), KeyCode::Left],
+        true,
+    );
+    assert_eq!(view.pending, None);
+    assert_eq!(vm.memory[0x200..0x202], [0xC3, 0]);
+}
+
+#[test]
+fn other_keys_are_left_to_the_emulator() {
+    let mut vm = Chip8::default();
+    let mut view = MemoryView::default();
+    assert_eq!(
+        view.input(KeyCode::Char(' '), &mut vm, true),
+        Outcome::Ignored
+    );
+    assert_eq!(view.input(KeyCode::Tab, &mut vm, true), Outcome::Ignored);
+    assert_eq!(view.input(KeyCode::Esc, &mut vm, true), Outcome::Leave);
+}
+
+#[test]
+fn scrolling_keeps_the_cursor_in_sight() {
+    let mut vm = Chip8::default();
+    let mut view = MemoryView::default();
+    view.scroll_into_view(4, 8);
+    assert_eq!(view.top, 0x200);
+    type_keys(&mut view, &mut vm, &[KeyCode::Down; 5], false);
+    view.scroll_into_view(4, 8);
+    assert_eq!((view.cursor, view.top), (0x228, 0x210));
+    view.input(KeyCode::Home, &mut vm, false);
+    view.scroll_into_view(4, 8);
+    assert_eq!(view.top, 0);
+    assert_eq!(memory_view::ascii(b'A'), 'A');
+    assert_eq!(memory_view::ascii(0x7F), '.');
+}
@@ -0,0 +1,82 @@
+use rusty_chip8::movie::{Movie, MovieError};
+use rusty_chip8::{Chip8, Keypad, Quirks, Variant};
+
+// 00E0: clear, C0FF: V0 = random, F10A: wait for a key into V1, F129: I = font of V1,
+// D015: draw it at (V0, V1), 1202: loop after the clear.
+const ROM: [u8; 12] = [
+    0x00, 0xE0, 0xC0, 0xFF, 0xF1, 0x0A, 0xF1, 0x29, 0xD0, 0x15, 0x12, 0x02,
+];
+
+fn keys(frame: usize) -> Keypad {
+    let mut keypad = Keypad::default();
+    if frame % 4 < 2 {
+        keypad.press((frame / 4 % 8) as u8);
+    }
+    keypad
+}
+
+fn record(frames: usize) -> Movie {
+    let mut vm = Chip8::with_variant(Variant::SuperChip, Quirks::super_chip());
+    vm.seed(2024);
+    vm.load(&ROM).unwrap();
+    let mut movie = Movie::new(&vm, 2024, 10);
+    for frame in 0..frames {
+        vm.run_frame(keys(frame), 10).unwrap();
+        movie.record(keys(frame), &vm);
+    }
+    movie
+}
+
+#[test]
+fn recorded_movies_replay_identically() {
+    let movie = record(60);
+    let bytes = movie.to_bytes();
+    let read = Movie::from_bytes(&bytes).unwrap();
+    assert_eq!(read, movie);
+    assert_eq!(read.variant, Variant::SuperChip);
+    assert_eq!(read.quirks, Quirks::super_chip());
+
+    let vm = read.replay(&ROM).unwrap();
+    let checksums: Vec<u64> = movie.frames.iter().map(|frame| frame.checksum).collect();
+    assert!(checksums.windows(2).any(|pair| pair[0] != pair[1]));
+    assert_eq!(
+        rusty_chip8::movie::checksum(vm.framebuffer()),
+        *checksums.last().unwrap()
+    );
+}
+
+#[test]
+fn replays_report_the_frame_they_desynchronize_at() {
+    let mut movie = record(60);
+    // Key 1 is held on frames 4 and 5, holding key 8 instead draws another digit
+    // once it is released, on frame 6.
+    movie.frames[4].keypad = Keypad::from_bits(1 << 8);
+    movie.frames[5].keypad = Keypad::from_bits(1 << 8);
+    match movie.replay(&ROM) {
+        Err(MovieError::Desync { frame, .. }) => assert_eq!(frame, 6),
+        other => panic!("expected a desync, got {:?}", other.map(|_| ())),
+    }
+
+    let mut movie = record(10);
+    movie.seed += 1;
+    assert!(matches!(movie.replay(&ROM), Err(MovieError::Desync { .. })));
+}
+
+#[test]
+fn movies_of_another_rom_are_refused() {
+    let movie = record(5);
+    assert!(matches!(
+        movie.replay(&[0x12, 0x00]),
+        Err(MovieError::RomMismatch { .. })
+    ));
+}
+
+#[test]
+fn invalid_movies_are_refused() {
+    let bytes = record(5).to_bytes();
+    assert_eq!(Movie::from_bytes(b"C8ST"), Err(MovieError::NotAMovie));
+    assert_eq!(
+        Movie::from_bytes(&bytes[..bytes.len() - 3]),
+        Err(MovieError::Truncated)
+    );
+}
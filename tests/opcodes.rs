@@ -0,0 +1,282 @@
+use rusty_chip8::{Chip8, Keypad, Quirks};
+
+const X: usize = 0x1;
+const Y: usize = 0x2;
+const VF: usize = 0xF;
+
+// Runs `opcode` once on a machine prepared by `setup`, the opcode being at 0x200.
+fn execute(vm: &mut Chip8, opcode: u16, setup: impl FnOnce(&mut Chip8)) {
+    vm.program_counter = 0x200;
+    vm.memory[0x200..0x202].copy_from_slice(&opcode.to_be_bytes());
+    setup(vm);
+    vm.cycle(Keypad::default()).unwrap();
+}
+
+fn machine() -> Chip8 {
+    let mut vm = Chip8::new(Quirks::cosmac_vip());
+    vm.seed(0);
+    vm
+}
+
+// Result and flag of the 8XYN arithmetic instructions, the shifts reading VY as on the VIP.
+fn reference(n: u16, vx: u8, vy: u8) -> (u8, u8) {
+    match n {
+        0x4 => (vx.wrapping_add(vy), (vx as u16 + vy as u16 > 0xFF) as u8),
+        0x5 => (vx.wrapping_sub(vy), (vx >= vy) as u8),
+        0x6 => (vy >> 1, vy & 0x01),
+        0x7 => (vy.wrapping_sub(vx), (vy >= vx) as u8),
+        0xE => (vy << 1, vy >> 7),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn arithmetic_sets_result_and_flag_for_every_value() {
+    let mut vm = machine();
+    for n in [0x4, 0x5, 0x6, 0x7, 0xE] {
+        let opcode = 0x8000 | (X as u16) << 8 | (Y as u16) << 4 | n;
+        for vx in 0..=0xFF {
+            for vy in 0..=0xFF {
+                execute(&mut vm, opcode, |vm| {
+                    vm.register[X] = vx;
+                    vm.register[Y] = vy;
+                    vm.register[VF] = 0xAA;
+                });
+                let (result, flag) = reference(n, vx, vy);
+                assert_eq!(
+                    (vm.register[X], vm.register[VF]),
+                    (result, flag),
+                    "{:#06X} with VX={} VY={}",
+                    opcode,
+                    vx,
+                    vy
+                );
+                assert_eq!(vm.register[Y], vy);
+            }
+        }
+    }
+}
+
+#[test]
+fn flag_wins_when_x_is_f() {
+    let mut vm = machine();
+    for n in [0x4, 0x5, 0x6, 0x7, 0xE] {
+        let opcode = 0x8F00 | (Y as u16) << 4 | n;
+        for vf in 0..=0xFF {
+            for vy in 0..=0xFF {
+                execute(&mut vm, opcode, |vm| {
+                    vm.register[VF] = vf;
+                    vm.register[Y] = vy;
+                });
+                let (_, flag) = reference(n, vf, vy);
+                assert_eq!(
+                    vm.register[VF], flag,
+                    "{:#06X} with VF={} VY={}",
+                    opcode, vf, vy
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn operands_are_read_before_the_flag_when_y_is_f() {
+    let mut vm = machine();
+    for n in [0x4, 0x5, 0x6, 0x7, 0xE] {
+        let opcode = 0x80F0 | (X as u16) << 8 | n;
+        for vx in 0..=0xFF {
+            for vf in 0..=0xFF {
+                execute(&mut vm, opcode, |vm| {
+                    vm.register[X] = vx;
+                    vm.register[VF] = vf;
+                });
+                let (result, flag) = reference(n, vx, vf);
+                assert_eq!(
+                    (vm.register[X], vm.register[VF]),
+                    (result, flag),
+                    "{:#06X} with VX={} VF={}",
+                    opcode,
+                    vx,
+                    vf
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn logic_operations_reset_the_flag_last() {
+    let mut vm = machine();
+    for (n, operation) in [
+        (0x1, (|a, b| a | b) as fn(u8, u8) -> u8),
+        (0x2, |a, b| a & b),
+        (0x3, |a, b| a ^ b),
+    ] {
+        for vx in 0..=0xFF {
+            let vy = 0x5A;
+            execute(
+                &mut vm,
+                0x8000 | (X as u16) << 8 | (Y as u16) << 4 | n,
+                |vm| {
+                    vm.register[X] = vx;
+                    vm.register[Y] = vy;
+                    vm.register[VF] = 0xAA;
+                },
+            );
+            assert_eq!(vm.register[X], operation(vx, vy));
+            assert_eq!(vm.register[VF], 0);
+            execute(&mut vm, 0x8F00 | (Y as u16) << 4 | n, |vm| {
+                vm.register[VF] = vx;
+                vm.register[Y] = vy;
+            });
+            assert_eq!(vm.register[VF], 0);
+        }
+    }
+}
+
+#[test]
+fn additions_without_carry_leave_the_flag_alone() {
+    let mut vm = machine();
+    for vx in 0..=0xFF {
+        execute(&mut vm, 0x71FF, |vm| {
+            vm.register[X] = vx;
+            vm.register[VF] = 0xAA;
+        });
+        assert_eq!(vm.register[X], vx.wrapping_add(0xFF));
+        assert_eq!(vm.register[VF], 0xAA);
+
+        execute(&mut vm, 0xF11E, |vm| {
+            vm.register[X] = vx;
+            vm.memory_index = 0xFFF;
+            vm.register[VF] = 0xAA;
+        });
+        assert_eq!(vm.memory_index, (0xFFF + vx as u16) & 0xFFF);
+        assert_eq!(vm.register[VF], 0xAA);
+    }
+    execute(&mut vm, 0x7F01, |vm| vm.register[VF] = 0xFF);
+    assert_eq!(vm.register[VF], 0x00);
+}
+
+#[test]
+fn register_loads_and_moves() {
+    let mut vm = machine();
+    execute(&mut vm, 0x6F42, |_| {});
+    assert_eq!(vm.register[VF], 0x42);
+    execute(&mut vm, 0x81F0, |vm| vm.register[VF] = 0x24);
+    assert_eq!(vm.register[X], 0x24);
+    execute(&mut vm, 0xC10F, |_| {});
+    assert_eq!(vm.register[X] & 0xF0, 0);
+    execute(&mut vm, 0xA123, |_| {});
+    assert_eq!(vm.memory_index, 0x123);
+}
+
+#[test]
+fn skips_compare_registers_and_values() {
+    let mut vm = machine();
+    let cases: [(u16, u8, u8, bool); 8] = [
+        (0x3142, 0x42, 0, true),
+        (0x3142, 0x41, 0, false),
+        (0x4142, 0x42, 0, false),
+        (0x4142, 0x41, 0, true),
+        (0x5120, 7, 7, true),
+        (0x5120, 7, 8, false),
+        (0x9120, 7, 7, false),
+        (0x9120, 7, 8, true),
+    ];
+    for (opcode, vx, vy, skips) in cases {
+        execute(&mut vm, opcode, |vm| {
+            vm.register[X] = vx;
+            vm.register[Y] = vy;
+        });
+        let expected = if skips { 0x204 } else { 0x202 };
+        assert_eq!(vm.program_counter, expected, "{:#06X}", opcode);
+    }
+}
+
+#[test]
+fn jumps_and_subroutines() {
+    let mut vm = machine();
+    execute(&mut vm, 0x1ABC, |_| {});
+    assert_eq!(vm.program_counter, 0xABC);
+    execute(&mut vm, 0x2ABC, |vm| vm.stack.clear());
+    assert_eq!((vm.program_counter, vm.stack.clone()), (0xABC, vec![0x200]));
+    execute(&mut vm, 0x00EE, |vm| vm.stack = vec![0x300]);
+    assert_eq!(vm.program_counter, 0x302);
+    execute(&mut vm, 0xB300, |vm| vm.register[0] = 0x10);
+    assert_eq!(vm.program_counter, 0x310);
+}
+
+#[test]
+fn font_digits_cover_every_nibble() {
+    let mut vm = machine();
+    for value in 0..=0xFF {
+        execute(&mut vm, 0xFF29, |vm| vm.register[VF] = value);
+        assert_eq!(vm.memory_index, (value & 0x0F) as u16 * 5);
+    }
+    // The sprite of F: 0xF0, 0x80, 0xF0, 0x80, 0x80.
+    assert_eq!(&vm.memory[75..80], [0xF0, 0x80, 0xF0, 0x80, 0x80]);
+}
+
+#[test]
+fn bcd_writes_every_value_as_decimal_digits() {
+    let mut vm = machine();
+    for value in 0..=0xFF {
+        execute(&mut vm, 0xFF33, |vm| {
+            vm.register[VF] = value;
+            vm.memory_index = 0x300;
+        });
+        assert_eq!(
+            vm.memory[0x300..0x303],
+            [value / 100, value / 10 % 10, value % 10]
+        );
+    }
+}
+
+#[test]
+fn store_and_restore_include_vf() {
+    let mut vm = machine();
+    execute(&mut vm, 0xFF55, |vm| {
+        vm.register = core::array::from_fn(|index| index as u8 + 1);
+        vm.memory_index = 0x300;
+    });
+    assert_eq!(
+        vm.memory[0x300..0x310],
+        core::array::from_fn::<u8, 16, _>(|i| i as u8 + 1)
+    );
+    // The VIP leaves I past the last register.
+    assert_eq!(vm.memory_index, 0x310);
+
+    execute(&mut vm, 0xFF65, |vm| {
+        vm.register = [0; 16];
+        vm.memory_index = 0x300;
+    });
+    assert_eq!(vm.register[VF], 16);
+}
+
+#[test]
+fn timers_read_and_write() {
+    let mut vm = machine();
+    execute(&mut vm, 0xFF15, |vm| vm.register[VF] = 30);
+    assert_eq!(vm.delay_timer, 30);
+    execute(&mut vm, 0xF118, |vm| vm.register[X] = 40);
+    assert_eq!(vm.sound_timer, 40);
+    execute(&mut vm, 0xFF07, |vm| vm.delay_timer = 12);
+    assert_eq!(vm.register[VF], 12);
+}
+
+#[test]
+fn draw_reads_coordinates_before_flagging_collisions() {
+    let mut vm = machine();
+    // VF holds the X coordinate, the collision flag overwrites it afterward.
+    execute(&mut vm, 0xDF11, |vm| {
+        vm.register[VF] = 8;
+        vm.register[X] = 0;
+        vm.memory_index = 0x300;
+        vm.memory[0x300] = 0x80;
+    });
+    assert!(vm.pixel(8, 0));
+    assert_eq!(vm.register[VF], 0);
+    execute(&mut vm, 0xDF11, |vm| vm.register[VF] = 8);
+    assert!(!vm.pixel(8, 0));
+    assert_eq!(vm.register[VF], 1);
+}
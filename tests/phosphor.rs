@@ -0,0 +1,72 @@
+use rusty_chip8::phosphor::{Persistence, Phosphor};
+use rusty_chip8::{Chip8, Quirks, Variant};
+
+// Feeds the frames to the filter, each frame setting the first pixel, and returns the first
+// pixel and its brightness after each one.
+fn filter(phosphor: &mut Phosphor, frames: &[u8]) -> Vec<(u8, u8)> {
+    let mut vm = Chip8::default();
+    frames
+        .iter()
+        .map(|&pixel| {
+            vm.gfx[0] = pixel;
+            phosphor.update(&vm);
+            let screen = phosphor.screen();
+            (screen.pixels[0], screen.brightness.unwrap()[0])
+        })
+        .collect()
+}
+
+#[test]
+fn cleared_pixels_fade_out_over_the_decay_frames() {
+    let mut phosphor = Phosphor::new(Persistence::Decay, 3);
+    assert_eq!(
+        filter(&mut phosphor, &[2, 0, 0, 0, 0, 0]),
+        [(2, 255), (2, 191), (2, 127), (2, 63), (0, 0), (0, 0)]
+    );
+    // Pixels drawn again while fading are fully bright.
+    assert_eq!(
+        filter(&mut phosphor, &[1, 0, 1]),
+        [(1, 255), (1, 191), (1, 255)]
+    );
+}
+
+#[test]
+fn blending_combines_the_last_two_frames() {
+    let mut phosphor = Phosphor::new(Persistence::Blend, 3);
+    assert_eq!(
+        filter(&mut phosphor, &[1, 0, 0, 2, 1]),
+        [(1, 255), (1, 255), (0, 255), (2, 255), (3, 255)]
+    );
+}
+
+#[test]
+fn without_persistence_the_framebuffer_goes_through() {
+    let mut phosphor = Phosphor::new(Persistence::Off, 3);
+    assert_eq!(
+        filter(&mut phosphor, &[1, 0, 3]),
+        [(1, 255), (0, 255), (3, 255)]
+    );
+}
+
+#[test]
+fn resolution_changes_clear_the_persistence() {
+    let mut vm = Chip8::with_variant(Variant::SuperChip, Quirks::super_chip());
+    let mut phosphor = Phosphor::new(Persistence::Decay, 3);
+    vm.gfx.iter_mut().for_each(|pixel| *pixel = 1);
+    phosphor.update(&vm);
+    // 00FF switches to the 128x64 high resolution, clearing the screen.
+    vm.memory[0x200..0x202].copy_from_slice(&[0x00, 0xFF]);
+    vm.cycle(Default::default()).unwrap();
+    phosphor.update(&vm);
+    let screen = phosphor.screen();
+    assert_eq!((screen.width, screen.height), (128, 64));
+    assert!(screen.pixels.iter().all(|&pixel| pixel == 0));
+}
+
+#[test]
+fn persistence_parses_from_its_name() {
+    assert_eq!("Decay".parse(), Ok(Persistence::Decay));
+    assert_eq!("blend".parse(), Ok(Persistence::Blend));
+    assert_eq!("off".parse(), Ok(Persistence::Off));
+    assert!("ghost".parse::<Persistence>().is_err());
+}
@@ -0,0 +1,81 @@
+use rusty_chip8::{Chip8, Keypad, Quirks};
+
+fn run(quirks: Quirks, rom: &[u8], cycles: usize) -> Chip8 {
+    let mut vm = Chip8::new(quirks);
+    vm.load(rom).unwrap();
+    for _ in 0..cycles {
+        vm.cycle(Keypad::default()).unwrap();
+    }
+    vm
+}
+
+#[test]
+fn shift_uses_vy_on_cosmac_vip() {
+    // 6103: V1 = 3, 6208: V2 = 8, 8126: V1 = V2 >> 1 (vip) or V1 >> 1.
+    let rom = [0x61, 0x03, 0x62, 0x08, 0x81, 0x26];
+    let vm = run(Quirks::cosmac_vip(), &rom, 3);
+    assert_eq!(vm.register[1], 4);
+    assert_eq!(vm.register[0xF], 0);
+
+    let vm = run(Quirks::chip48(), &rom, 3);
+    assert_eq!(vm.register[1], 1);
+    assert_eq!(vm.register[0xF], 1);
+}
+
+#[test]
+fn index_increments_by_x_plus_one_on_cosmac_vip() {
+    // A300: I = 0x300, F255: store V0..V2.
+    let rom = [0xA3, 0x00, 0xF2, 0x55];
+    assert_eq!(run(Quirks::cosmac_vip(), &rom, 2).memory_index, 0x303);
+    assert_eq!(run(Quirks::super_chip(), &rom, 2).memory_index, 0x300);
+}
+
+#[test]
+fn index_increments_by_x_on_chip48() {
+    // A300: I = 0x300, F265: load V0..V2.
+    let rom = [0xA3, 0x00, 0xF2, 0x65];
+    assert_eq!(run(Quirks::chip48(), &rom, 2).memory_index, 0x302);
+    assert_ne!(Quirks::chip48(), Quirks::super_chip());
+}
+
+#[test]
+fn jump_uses_vx_on_chip48() {
+    // 6010: V0 = 0x10, 6220: V2 = 0x20, B240: jump to 0x240 + V0 (vip) or V2.
+    let rom = [0x60, 0x10, 0x62, 0x20, 0xB2, 0x40];
+    assert_eq!(run(Quirks::cosmac_vip(), &rom, 3).program_counter, 0x250);
+    assert_eq!(run(Quirks::chip48(), &rom, 3).program_counter, 0x260);
+}
+
+#[test]
+fn logic_operations_reset_vf_on_cosmac_vip() {
+    // 6F05: VF = 5, 8011: V0 |= V1.
+    let rom = [0x6F, 0x05, 0x80, 0x11];
+    assert_eq!(run(Quirks::cosmac_vip(), &rom, 2).register[0xF], 0);
+    assert_eq!(run(Quirks::super_chip(), &rom, 2).register[0xF], 5);
+}
+
+#[test]
+fn sprites_clip_or_wrap_at_screen_edges() {
+    // 603E: V0 = 62, A000: I = font "0", D015: draw at (62, 0).
+    let rom = [0x60, 0x3E, 0xA0, 0x00, 0xD0, 0x15];
+    let clipped = Quirks::cosmac_vip();
+    let vm = run(clipped, &rom, 3);
+    assert!(vm.pixel(63, 0));
+    assert!(!vm.pixel(0, 0));
+
+    let wrapped = Quirks {
+        clip_sprites: false,
+        ..clipped
+    };
+    let vm = run(wrapped, &rom, 3);
+    assert!(vm.pixel(63, 0));
+    assert!(vm.pixel(0, 0));
+}
+
+#[test]
+fn profiles_are_parsed_by_name() {
+    assert_eq!("vip".parse::<Quirks>().unwrap(), Quirks::cosmac_vip());
+    assert_eq!("CHIP48".parse::<Quirks>().unwrap(), Quirks::chip48());
+    assert_eq!("schip".parse::<Quirks>().unwrap(), Quirks::super_chip());
+    assert!("gameboy".parse::<Quirks>().is_err());
+}
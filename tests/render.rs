@@ -0,0 +1,154 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::Color;
+use rusty_chip8::render::{
+    BlockCanvas, Braille, HalfBlock, Palette, Renderer, RendererKind, Screen,
+};
+use rusty_chip8::Chip8;
+
+const PALETTE: Palette = Palette {
+    background: Color::Black,
+    planes: [Color::White, Color::Red, Color::Yellow],
+};
+
+// Screen parsed from rows of `.` for unlit pixels and plane digits for lit ones.
+fn pixels(rows: &[&str]) -> Vec<u8> {
+    rows.iter()
+        .flat_map(|row| {
+            row.chars()
+                .map(|pixel| pixel.to_digit(4).unwrap_or(0) as u8)
+        })
+        .collect()
+}
+
+// Renders the screen into a buffer of the given size, returning the symbol and colours of
+// every cell, row after row.
+fn render(
+    renderer: &dyn Renderer,
+    rows: &[&str],
+    palette: &Palette,
+    (width, height): (u16, u16),
+) -> Vec<(String, Color, Color)> {
+    let pixels = pixels(rows);
+    let screen = Screen {
+        pixels: &pixels,
+        width: rows[0].len(),
+        height: rows.len(),
+        brightness: None,
+    };
+    let area = Rect::new(0, 0, width, height);
+    let mut buffer = Buffer::empty(area);
+    renderer.render(screen, palette, area, &mut buffer);
+    buffer
+        .content()
+        .iter()
+        .map(|cell| (cell.symbol().to_string(), cell.fg, cell.bg))
+        .collect()
+}
+
+fn cell(symbol: &str, foreground: Color, background: Color) -> (String, Color, Color) {
+    (symbol.to_string(), foreground, background)
+}
+
+#[test]
+fn half_blocks_draw_two_rows_per_cell() {
+    let cells = render(&HalfBlock, &["1.12.3", "..1121"], &PALETTE, (6, 1));
+    assert_eq!(
+        cells,
+        [
+            cell("▀", Color::White, Color::Black),
+            cell(" ", Color::Black, Color::Black),
+            cell("█", Color::White, Color::Black),
+            cell("▀", Color::Red, Color::White),
+            cell("▄", Color::Red, Color::Black),
+            cell("▀", Color::Yellow, Color::White),
+        ]
+    );
+}
+
+#[test]
+fn half_blocks_keep_the_default_colour_in_the_foreground() {
+    let cells = render(&HalfBlock, &["2", "1"], &Palette::default(), (1, 1));
+    assert_eq!(cells, [cell("▄", Color::Reset, Color::LightRed)]);
+}
+
+#[test]
+fn braille_draws_two_by_four_pixels_per_cell() {
+    let rows = ["1.11", ".1.1", "..13", "1..1"];
+    let cells = render(&Braille, &rows, &PALETTE, (2, 1));
+    // Dots 1, 5 and 7 on the left, 1, 3, 4, 5, 6 and 8 on the right.
+    assert_eq!(
+        cells,
+        [
+            cell("⡑", Color::White, Color::Black),
+            cell("⢽", Color::Yellow, Color::Black),
+        ]
+    );
+}
+
+#[test]
+fn renderers_clip_the_screen_to_the_area() {
+    let rows = ["1111", "1111", "1111", "1111"];
+    assert_eq!(render(&HalfBlock, &rows, &PALETTE, (2, 1)).len(), 2);
+    assert_eq!(render(&Braille, &rows, &PALETTE, (1, 1)).len(), 1);
+    let wider = render(&HalfBlock, &rows, &PALETTE, (6, 3));
+    assert_eq!(wider[4], cell(" ", Color::Reset, Color::Reset));
+    assert_eq!(wider[6], cell("█", Color::White, Color::Black));
+}
+
+#[test]
+fn renderers_report_the_cells_they_take() {
+    let vm = Chip8::default();
+    let screen = Screen::from(&vm);
+    assert_eq!((screen.width, screen.height), (64, 32));
+    assert_eq!(HalfBlock.size(64, 32), Some((64, 16)));
+    assert_eq!(Braille.size(128, 64), Some((64, 16)));
+    assert_eq!(BlockCanvas.size(64, 32), None);
+}
+
+#[test]
+fn renderers_parse_from_their_names() {
+    let kinds: Vec<RendererKind> = ["half-block", "Braille", "canvas"]
+        .iter()
+        .map(|name| name.parse().unwrap())
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            RendererKind::HalfBlock,
+            RendererKind::Braille,
+            RendererKind::Canvas
+        ]
+    );
+    assert!("sixel".parse::<RendererKind>().is_err());
+    assert_eq!(RendererKind::default(), RendererKind::HalfBlock);
+}
+
+#[test]
+fn fading_pixels_are_dimmed() {
+    let palette = Palette {
+        background: Color::Rgb(0, 0, 0),
+        planes: [Color::Rgb(255, 200, 0), Color::LightRed, Color::White],
+    };
+    assert_eq!(palette.shade(1, 255), Some(Color::Rgb(255, 200, 0)));
+    assert_eq!(palette.shade(1, 51), Some(Color::Rgb(51, 40, 0)));
+    assert_eq!(palette.shade(1, 0), None);
+    assert_eq!(palette.shade(2, 200), Some(Color::Red));
+    assert_eq!(palette.shade(3, 100), Some(Color::DarkGray));
+
+    let pixels = [1, 1];
+    let screen = Screen {
+        pixels: &pixels,
+        width: 1,
+        height: 2,
+        brightness: Some(&[255, 51]),
+    };
+    let area = Rect::new(0, 0, 1, 1);
+    let mut buffer = Buffer::empty(area);
+    HalfBlock.render(screen, &palette, area, &mut buffer);
+    let cell = &buffer[(0, 0)];
+    assert_eq!(
+        (cell.symbol(), cell.fg, cell.bg),
+        ("▀", Color::Rgb(255, 200, 0), Color::Rgb(51, 40, 0))
+    );
+}
@@ -0,0 +1,82 @@
+use rusty_chip8::rewind::{History, DEFAULT_REWIND_FRAMES};
+use rusty_chip8::{Chip8, Keypad};
+
+// 7001: V0 += 1, C1FF: V1 = random, 1200: loop.
+const ROM: [u8; 6] = [0x70, 0x01, 0xC1, 0xFF, 0x12, 0x00];
+
+fn running(frames: usize) -> Chip8 {
+    let mut vm = Chip8 {
+        history: History::new(DEFAULT_REWIND_FRAMES),
+        ..Chip8::default()
+    };
+    vm.seed(5);
+    vm.load(&ROM).unwrap();
+    for _ in 0..frames {
+        vm.run_frame(Keypad::default(), 3).unwrap();
+    }
+    vm
+}
+
+#[test]
+fn rewinding_restores_the_machine_as_it_was() {
+    let mut vm = running(10);
+    let states: Vec<Vec<u8>> = (0..5)
+        .map(|_| {
+            vm.run_frame(Keypad::default(), 3).unwrap();
+            vm.save_state()
+        })
+        .collect();
+
+    assert_eq!(vm.rewind(1), 1);
+    assert_eq!(vm.save_state(), states[3]);
+    assert_eq!(vm.rewind(3), 3);
+    assert_eq!(vm.save_state(), states[0]);
+
+    // The rewound machine replays the same frames, random numbers included.
+    vm.run_frame(Keypad::default(), 3).unwrap();
+    assert_eq!(vm.save_state(), states[1]);
+}
+
+#[test]
+fn history_is_bounded() {
+    let mut vm = Chip8 {
+        history: History::new(4),
+        ..Chip8::default()
+    };
+    vm.load(&ROM).unwrap();
+    for _ in 0..10 {
+        vm.run_frame(Keypad::default(), 3).unwrap();
+    }
+    assert_eq!(vm.history.len(), 4);
+    assert_eq!(vm.rewind(100), 4);
+    assert_eq!(vm.register[0], 6);
+    assert!(vm.history.is_empty());
+    assert_eq!(vm.rewind(1), 0);
+}
+
+#[test]
+fn frames_are_stored_as_small_patches() {
+    let vm = running(100);
+    let snapshot = vm.save_state().len();
+    assert_eq!(vm.history.len(), 99);
+    assert!(vm.history.stored_bytes() < snapshot + 99 * 64);
+}
+
+#[test]
+fn history_is_disabled_by_default() {
+    let mut vm = Chip8::default();
+    assert_eq!(vm.history.capacity(), 0);
+    vm.load(&ROM).unwrap();
+    vm.run_frame(Keypad::default(), 3).unwrap();
+    vm.run_frame(Keypad::default(), 3).unwrap();
+    assert_eq!(vm.rewind(1), 0);
+    assert_eq!(vm.register[0], 2);
+}
+
+#[test]
+fn loading_a_rom_forgets_the_history() {
+    let mut vm = running(10);
+    vm.load(&[0x12, 0x00]).unwrap();
+    assert!(vm.history.is_empty());
+    assert_eq!(vm.history.stored_bytes(), 0);
+}
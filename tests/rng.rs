@@ -0,0 +1,35 @@
+use rusty_chip8::{Chip8, Keypad, Rng};
+
+// C0FF: V0 = random, C1FF: V1 = random, C2FF: V2 = random, 1206: loop.
+const RANDOM_ROM: [u8; 8] = [0xC0, 0xFF, 0xC1, 0xFF, 0xC2, 0xFF, 0x12, 0x06];
+
+fn run_seeded(seed: u64) -> Chip8 {
+    let mut vm = Chip8::default();
+    vm.seed(seed);
+    vm.load(&RANDOM_ROM).unwrap();
+    vm.run_frame(Keypad::default(), 3).unwrap();
+    vm
+}
+
+#[test]
+fn same_seed_gives_same_random_values() {
+    assert_eq!(run_seeded(42).register, run_seeded(42).register);
+    assert_ne!(run_seeded(42).register, run_seeded(43).register);
+}
+
+#[test]
+fn cloned_machine_continues_the_same_sequence() {
+    let mut vm = run_seeded(7);
+    let mut clone = vm.clone();
+    vm.run_frame(Keypad::default(), 10).unwrap();
+    clone.run_frame(Keypad::default(), 10).unwrap();
+    assert_eq!(vm.rng, clone.rng);
+}
+
+#[test]
+fn generator_resumes_from_its_state() {
+    let mut rng = Rng::new(1234);
+    rng.next_u64();
+    let mut resumed = Rng::new(rng.state());
+    assert_eq!(rng.next_u64(), resumed.next_u64());
+}
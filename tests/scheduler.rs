@@ -0,0 +1,73 @@
+use rusty_chip8::scheduler::{instructions_per_frame, Scheduler};
+use rusty_chip8::{Chip8, Keypad, Quirks};
+use std::time::{Duration, Instant};
+
+#[test]
+fn run_frame_executes_the_requested_instruction_count() {
+    let mut vm = Chip8::new(Quirks::chip48());
+    // 7001: V0 += 1, 1200: loop.
+    vm.load(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+    vm.run_frame(Keypad::default(), 10).unwrap();
+    assert_eq!(vm.register[0], 5);
+}
+
+#[test]
+fn timers_tick_once_per_frame_whatever_the_instruction_count() {
+    let mut vm = Chip8::new(Quirks::chip48());
+    // 603C: V0 = 60, F015: delay = V0, F018: sound = V0, 1206: loop.
+    vm.load(&[0x60, 0x3C, 0xF0, 0x15, 0xF0, 0x18, 0x12, 0x06])
+        .unwrap();
+    vm.run_frame(Keypad::default(), 100).unwrap();
+    assert_eq!(vm.delay_timer, 59);
+    assert_eq!(vm.sound_timer, 59);
+    for _ in 0..59 {
+        vm.run_frame(Keypad::default(), 100).unwrap();
+    }
+    assert_eq!(vm.delay_timer, 0);
+}
+
+#[test]
+fn cycle_leaves_timers_untouched() {
+    let mut vm = Chip8::default();
+    // 6005: V0 = 5, F015: delay = V0, 1204: loop.
+    vm.load(&[0x60, 0x05, 0xF0, 0x15, 0x12, 0x04]).unwrap();
+    for _ in 0..10 {
+        vm.cycle(Keypad::default()).unwrap();
+    }
+    assert_eq!(vm.delay_timer, 5);
+}
+
+#[test]
+fn display_wait_ends_the_frame_after_a_draw() {
+    // D001: draw, 7101: V1 += 1, 1200: loop.
+    let rom = [0xD0, 0x01, 0x71, 0x01, 0x12, 0x00];
+    let mut vm = Chip8::new(Quirks::cosmac_vip());
+    vm.load(&rom).unwrap();
+    vm.run_frame(Keypad::default(), 11).unwrap();
+    assert_eq!(vm.program_counter, 0x202);
+
+    let mut vm = Chip8::new(Quirks::chip48());
+    vm.load(&rom).unwrap();
+    vm.run_frame(Keypad::default(), 11).unwrap();
+    assert_eq!(vm.register[1], 4);
+}
+
+#[test]
+fn clock_speed_is_converted_to_instructions_per_frame() {
+    assert_eq!(instructions_per_frame(660), 11);
+    assert_eq!(instructions_per_frame(10), 1);
+}
+
+#[test]
+fn scheduler_reports_elapsed_frames() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new(start);
+    assert_eq!(scheduler.frames_due(start), 1);
+    assert_eq!(scheduler.frames_due(start), 0);
+    assert_eq!(scheduler.frames_due(start + Duration::from_millis(40)), 2);
+    assert!(
+        scheduler.until_next_frame(start + Duration::from_millis(40)) <= Duration::from_millis(17)
+    );
+    // A long stall is not caught up entirely.
+    assert_eq!(scheduler.frames_due(start + Duration::from_secs(10)), 4);
+}
@@ -0,0 +1,96 @@
+use rusty_chip8::state::{StateError, STATE_VERSION};
+use rusty_chip8::{Chip8, Keypad, Quirks, Variant};
+
+// C0FF: V0 = random, A000: I = font "0", D005: draw it at (V0, V0),
+// 7101: V1 += 1, 2200: call the start again.
+const ROM: [u8; 10] = [0xC0, 0xFF, 0xA0, 0x00, 0xD0, 0x05, 0x71, 0x01, 0x22, 0x00];
+
+fn running(quirks: Quirks) -> Chip8 {
+    let mut vm = Chip8::with_variant(Variant::SuperChip, quirks);
+    vm.seed(99);
+    vm.load(&ROM).unwrap();
+    vm.stack_limit = 64;
+    vm.sound_timer = 10;
+    vm.run_frame(Keypad::default(), 12).unwrap();
+    vm.delay_timer = 30;
+    vm
+}
+
+fn assert_same_machine(a: &Chip8, b: &Chip8) {
+    assert_eq!(a.save_state(), b.save_state());
+    assert_eq!(a.framebuffer(), b.framebuffer());
+    assert_eq!(a.register, b.register);
+    assert_eq!(a.stack, b.stack);
+    assert_eq!(a.rng, b.rng);
+    assert_eq!(a.quirks, b.quirks);
+    assert_eq!((a.buzzer, a.sound_frames), (b.buzzer, b.sound_frames));
+}
+
+#[test]
+fn loading_a_state_resumes_the_machine_where_it_was_saved() {
+    let mut vm = running(Quirks::chip48());
+    let saved = vm.clone();
+    let state = vm.save_state();
+    vm.run_frame(Keypad::default(), 20).unwrap();
+    assert_ne!(vm.save_state(), state);
+
+    vm.load_state(&state).unwrap();
+    assert_same_machine(&vm, &saved);
+    // The buzzer keeps sounding, its tone picking up where it was.
+    assert_eq!((vm.buzzer, vm.sound_frames), (true, 1));
+
+    // Both continue identically, random numbers included.
+    let mut original = saved;
+    vm.run_frame(Keypad::default(), 20).unwrap();
+    original.run_frame(Keypad::default(), 20).unwrap();
+    assert_same_machine(&vm, &original);
+}
+
+#[test]
+fn states_restore_quirks_and_variant() {
+    let state = running(Quirks::chip48()).save_state();
+    let mut vm = Chip8::with_variant(Variant::Chip8, Quirks::cosmac_vip());
+    vm.load(&ROM).unwrap();
+    vm.load_state(&state).unwrap();
+    assert_eq!(vm.quirks, Quirks::chip48());
+    assert_eq!(vm.variant, Variant::SuperChip);
+    assert_eq!(vm.stack_limit, 64);
+}
+
+#[test]
+fn states_of_another_rom_are_refused() {
+    let state = running(Quirks::default()).save_state();
+    let mut vm = Chip8::default();
+    vm.load(&[0x12, 0x00]).unwrap();
+    let before = vm.save_state();
+    assert!(matches!(
+        vm.load_state(&state),
+        Err(StateError::RomMismatch { .. })
+    ));
+    assert_eq!(vm.save_state(), before);
+}
+
+#[test]
+fn invalid_states_are_refused() {
+    let mut vm = running(Quirks::default());
+    let state = vm.save_state();
+    assert_eq!(vm.load_state(b"not a state"), Err(StateError::NotAState));
+    assert_eq!(
+        vm.load_state(&state[..state.len() - 1]),
+        Err(StateError::Truncated)
+    );
+
+    let mut future = state.clone();
+    future[4..6].copy_from_slice(&(STATE_VERSION + 1).to_le_bytes());
+    assert_eq!(
+        vm.load_state(&future),
+        Err(StateError::UnsupportedVersion(STATE_VERSION + 1))
+    );
+
+    let mut trailing = state;
+    trailing.push(0);
+    assert!(matches!(
+        vm.load_state(&trailing),
+        Err(StateError::Corrupted(_))
+    ));
+}
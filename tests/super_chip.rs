@@ -0,0 +1,103 @@
+use rusty_chip8::{Chip8, Keypad, Variant, HIRES_SCREEN_HEIGHT, HIRES_SCREEN_WIDTH};
+
+fn super_chip(rom: &[u8]) -> Chip8 {
+    let mut vm = Chip8::with_variant(Variant::SuperChip, Variant::SuperChip.default_quirks());
+    vm.load(rom).unwrap();
+    vm
+}
+
+fn run(vm: &mut Chip8, cycles: usize) {
+    for _ in 0..cycles {
+        vm.cycle(Keypad::default()).unwrap();
+    }
+}
+
+#[test]
+fn super_chip_opcodes_are_unknown_on_chip8() {
+    let mut vm = Chip8::default();
+    vm.load(&[0x00, 0xFF]).unwrap();
+    assert!(vm.cycle(Keypad::default()).is_err());
+}
+
+#[test]
+fn hires_switches_the_framebuffer_resolution() {
+    // 00FF: high resolution, 00FE: low resolution.
+    let mut vm = super_chip(&[0x00, 0xFF, 0x00, 0xFE]);
+    run(&mut vm, 1);
+    assert_eq!(
+        (vm.width(), vm.height()),
+        (HIRES_SCREEN_WIDTH, HIRES_SCREEN_HEIGHT)
+    );
+    assert_eq!(
+        vm.framebuffer().len(),
+        HIRES_SCREEN_WIDTH * HIRES_SCREEN_HEIGHT
+    );
+    run(&mut vm, 1);
+    assert_eq!((vm.width(), vm.height()), (64, 32));
+}
+
+#[test]
+fn big_sprites_are_16_by_16() {
+    // 00FF: hires, A300: I = 0x300, D010: draw a 16x16 sprite at (0, 0).
+    let mut vm = super_chip(&[0x00, 0xFF, 0xA3, 0x00, 0xD0, 0x10]);
+    vm.memory[0x300..0x320].fill(0xFF);
+    run(&mut vm, 3);
+    assert!(vm.pixel(15, 15));
+    assert!(!vm.pixel(16, 0));
+    assert!(!vm.pixel(0, 16));
+}
+
+#[test]
+fn big_sprites_are_8_by_16_in_low_resolution() {
+    // A300: I = 0x300, D010: draw an 8x16 sprite at (0, 0).
+    let mut vm = super_chip(&[0xA3, 0x00, 0xD0, 0x10]);
+    vm.memory[0x300..0x320].fill(0xFF);
+    run(&mut vm, 2);
+    assert!(vm.pixel(7, 15));
+    assert!(!vm.pixel(8, 0));
+    assert!(!vm.pixel(0, 16));
+}
+
+#[test]
+fn screen_scrolls_down_left_and_right() {
+    // A000: I = font "0", D011: draw its first row,
+    // 00C2: scroll down 2, 00FB: scroll right 4, 00FC: scroll left 4.
+    let mut vm = super_chip(&[0xA0, 0x00, 0xD0, 0x11, 0x00, 0xC2, 0x00, 0xFB, 0x00, 0xFC]);
+    run(&mut vm, 3);
+    assert!(!vm.pixel(0, 0));
+    assert!(vm.pixel(0, 2) && vm.pixel(3, 2) && !vm.pixel(4, 2));
+    run(&mut vm, 1);
+    assert!(!vm.pixel(0, 2) && vm.pixel(4, 2) && vm.pixel(7, 2));
+    run(&mut vm, 1);
+    assert!(vm.pixel(0, 2) && !vm.pixel(4, 2));
+}
+
+#[test]
+fn exit_stops_the_machine() {
+    // 00FD: exit, 6001: never executed.
+    let mut vm = super_chip(&[0x00, 0xFD, 0x60, 0x01]);
+    run(&mut vm, 2);
+    assert!(vm.exited);
+    assert_eq!(vm.register[0], 0);
+}
+
+#[test]
+fn big_font_is_addressed_by_fx30() {
+    // 6007: V0 = 7, F030: I = big font "7".
+    let mut vm = super_chip(&[0x60, 0x07, 0xF0, 0x30]);
+    run(&mut vm, 2);
+    let address = vm.memory_index as usize;
+    assert_eq!(
+        &vm.memory[address..address + 10],
+        &[0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18]
+    );
+}
+
+#[test]
+fn rpl_flags_are_saved_and_restored() {
+    // 6011: V0 = 0x11, 6122: V1 = 0x22, F175: save V0..V1, 6000: V0 = 0, F185: restore V0..V1.
+    let mut vm = super_chip(&[0x60, 0x11, 0x61, 0x22, 0xF1, 0x75, 0x60, 0x00, 0xF1, 0x85]);
+    run(&mut vm, 5);
+    assert_eq!(&vm.register[..2], &[0x11, 0x22]);
+    assert_eq!(&vm.rpl_flags[..2], &[0x11, 0x22]);
+}
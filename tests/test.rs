@@ -1,5 +1,62 @@
+use rusty_chip8::{Chip8, Keypad, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+#[test]
+fn rom_is_loaded_at_program_start() {
+    let mut vm = Chip8::default();
+    vm.load(&[0x12, 0x00]).unwrap();
+    assert_eq!(vm.program_counter, 0x200);
+    assert_eq!(&vm.memory[0x200..0x202], &[0x12, 0x00]);
+}
+
+#[test]
+fn cycle_executes_the_loaded_rom() {
+    let mut vm = Chip8::default();
+    // 6A2A: VA = 0x2A, 1202: jump to itself.
+    vm.load(&[0x6A, 0x2A, 0x12, 0x02]).unwrap();
+    assert_eq!(vm.cycle(Keypad::default()).unwrap(), 0x6A2A);
+    assert_eq!(vm.register[0xA], 0x2A);
+    assert_eq!(vm.cycle(Keypad::default()).unwrap(), 0x1202);
+    assert_eq!(vm.program_counter, 0x202);
+}
+
+#[test]
+fn sprites_are_drawn_into_the_framebuffer() {
+    let mut vm = Chip8::default();
+    // A000: I = font "0", D015: draw 5 rows at (V0, V1) = (0, 0).
+    vm.load(&[0xA0, 0x00, 0xD0, 0x15]).unwrap();
+    vm.cycle(Keypad::default()).unwrap();
+    vm.cycle(Keypad::default()).unwrap();
+    assert_eq!(vm.framebuffer().len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+    // First row of "0" is 0xF0.
+    assert!((0..4).all(|x| vm.pixel(x, 0)));
+    assert!(!vm.pixel(4, 0));
+    assert_eq!(vm.register[0xF], 0);
+}
+
 #[test]
+fn pixels_off_the_screen_are_never_lit() {
+    let mut vm = Chip8::default();
+    // 6101: V1 = 1, A000: I = font "0", D015: draw 5 rows at (V0, V1) = (0, 1).
+    vm.load(&[0x61, 0x01, 0xA0, 0x00, 0xD0, 0x15]).unwrap();
+    for _ in 0..3 {
+        vm.cycle(Keypad::default()).unwrap();
+    }
+    assert!(vm.pixel(0, 1) && !vm.pixel(SCREEN_WIDTH - 1, 0));
+    // Right after the last column of row 0 comes the first pixel of row 1 in the framebuffer.
+    assert!(!vm.pixel(SCREEN_WIDTH, 0));
+    assert_eq!(vm.planes_at(SCREEN_WIDTH, 0), 0);
+    assert!(!vm.pixel(0, SCREEN_HEIGHT));
+    assert!(!vm.pixel(usize::MAX, usize::MAX));
+}
 
-fn it_works() {
-    assert_eq!(2 + 2, 4);
+#[test]
+fn keypad_input_is_used_by_skip_instructions() {
+    let mut vm = Chip8::default();
+    // 6105: V1 = 5, E19E: skip if key V1 is pressed.
+    vm.load(&[0x61, 0x05, 0xE1, 0x9E]).unwrap();
+    vm.cycle(Keypad::default()).unwrap();
+    let mut keypad = Keypad::default();
+    keypad.press(0x5);
+    vm.cycle(keypad).unwrap();
+    assert_eq!(vm.program_counter, 0x206);
 }
@@ -0,0 +1,117 @@
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::Color;
+use rusty_chip8::render::Palette;
+use rusty_chip8::theme::{ColorDepth, Theme};
+
+const THEMES: [Theme; 4] = [Theme::Green, Theme::Amber, Theme::Lcd, Theme::HighContrast];
+
+fn colors(palette: Palette, depth: ColorDepth) -> Vec<Color> {
+    std::iter::once(palette.background)
+        .chain(palette.planes)
+        .map(|color| depth.convert(color))
+        .collect()
+}
+
+fn distinct(colors: &[Color]) -> bool {
+    colors
+        .iter()
+        .enumerate()
+        .all(|(index, color)| !colors[index + 1..].contains(color))
+}
+
+#[test]
+fn themes_parse_from_their_names() {
+    let themes: Vec<Theme> = ["classic", "green", "Amber", "lcd", "high-contrast"]
+        .iter()
+        .map(|name| name.parse().unwrap())
+        .collect();
+    assert_eq!(themes[0], Theme::Classic);
+    assert_eq!(themes[1..], THEMES);
+    assert!("solarized".parse::<Theme>().is_err());
+    assert_eq!(Theme::default().palette(), Palette::default());
+}
+
+#[test]
+fn themes_tell_every_plane_apart() {
+    for theme in THEMES {
+        let palette = theme.palette();
+        assert!(
+            distinct(&colors(palette, ColorDepth::TrueColor)),
+            "{:?}",
+            theme
+        );
+        assert!(
+            distinct(&colors(palette, ColorDepth::Indexed)),
+            "{:?}",
+            theme
+        );
+    }
+    for theme in [Theme::Green, Theme::Amber, Theme::HighContrast] {
+        assert!(
+            distinct(&colors(theme.palette(), ColorDepth::Ansi)),
+            "{:?}",
+            theme
+        );
+    }
+}
+
+#[test]
+fn color_depth_is_guessed_from_the_environment() {
+    let detect = ColorDepth::from_env;
+    assert_eq!(
+        detect(Some("truecolor"), Some("xterm")),
+        ColorDepth::TrueColor
+    );
+    assert_eq!(detect(Some("24bit"), None), ColorDepth::TrueColor);
+    assert_eq!(detect(None, Some("xterm-direct")), ColorDepth::TrueColor);
+    assert_eq!(detect(None, Some("xterm-256color")), ColorDepth::Indexed);
+    assert_eq!(detect(None, Some("screen-256color")), ColorDepth::Indexed);
+    assert_eq!(detect(None, Some("linux")), ColorDepth::Ansi);
+    assert_eq!(detect(None, None), ColorDepth::Ansi);
+    assert_eq!("256".parse(), Ok(ColorDepth::Indexed));
+    assert!("8".parse::<ColorDepth>().is_err());
+}
+
+#[test]
+fn colours_downgrade_to_the_closest_displayed_one() {
+    let indexed = |color| ColorDepth::Indexed.convert(color);
+    assert_eq!(indexed(Color::Rgb(0xFF, 0x00, 0x00)), Color::Indexed(196));
+    assert_eq!(indexed(Color::Rgb(0x5F, 0x87, 0xAF)), Color::Indexed(67));
+    assert_eq!(indexed(Color::Rgb(0x80, 0x80, 0x80)), Color::Indexed(244));
+    assert_eq!(indexed(Color::LightRed), Color::LightRed);
+
+    let ansi = |color| ColorDepth::Ansi.convert(color);
+    assert_eq!(ansi(Color::Rgb(0xFA, 0x05, 0x05)), Color::LightRed);
+    assert_eq!(ansi(Color::Rgb(0x10, 0x10, 0x10)), Color::Black);
+    assert_eq!(ansi(Color::Indexed(196)), Color::LightRed);
+    assert_eq!(ansi(Color::Indexed(250)), Color::Gray);
+    assert_eq!(ansi(Color::Reset), Color::Reset);
+
+    let color = Color::Rgb(0x12, 0x34, 0x56);
+    assert_eq!(ColorDepth::TrueColor.convert(color), color);
+}
+
+#[test]
+fn areas_are_converted_in_place() {
+    let mut buffer = Buffer::empty(Rect::new(0, 0, 3, 1));
+    for x in 0..3 {
+        buffer[(x, 0)]
+            .set_fg(Color::Rgb(0xFF, 0x00, 0x00))
+            .set_bg(Color::Rgb(0x00, 0x00, 0x00));
+    }
+    ColorDepth::Ansi.convert_area(&mut buffer, Rect::new(1, 0, 5, 1));
+    let cells: Vec<(Color, Color)> = buffer
+        .content()
+        .iter()
+        .map(|cell| (cell.fg, cell.bg))
+        .collect();
+    assert_eq!(
+        cells,
+        [
+            (Color::Rgb(0xFF, 0x00, 0x00), Color::Rgb(0x00, 0x00, 0x00)),
+            (Color::LightRed, Color::Black),
+            (Color::LightRed, Color::Black),
+        ]
+    );
+}
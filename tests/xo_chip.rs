@@ -0,0 +1,92 @@
+use rusty_chip8::{Chip8, Keypad, Variant, XO_CHIP_MEMORY_SIZE};
+
+fn xo_chip(rom: &[u8]) -> Chip8 {
+    let mut vm = Chip8::with_variant(Variant::XoChip, Variant::XoChip.default_quirks());
+    vm.load(rom).unwrap();
+    vm
+}
+
+fn run(vm: &mut Chip8, cycles: usize) {
+    for _ in 0..cycles {
+        vm.cycle(Keypad::default()).unwrap();
+    }
+}
+
+#[test]
+fn memory_is_64_kib() {
+    let vm = xo_chip(&[]);
+    assert_eq!(vm.memory.len(), XO_CHIP_MEMORY_SIZE);
+}
+
+#[test]
+fn long_index_load_reads_the_next_word() {
+    // F000 ABCD: I = 0xABCD.
+    let mut vm = xo_chip(&[0xF0, 0x00, 0xAB, 0xCD]);
+    run(&mut vm, 1);
+    assert_eq!(vm.memory_index, 0xABCD);
+    assert_eq!(vm.program_counter, 0x204);
+}
+
+#[test]
+fn skips_step_over_long_index_load() {
+    // 3000: skip since V0 == 0, F000 1234: skipped, 6101: V1 = 1.
+    let mut vm = xo_chip(&[0x30, 0x00, 0xF0, 0x00, 0x12, 0x34, 0x61, 0x01]);
+    run(&mut vm, 2);
+    assert_eq!(vm.memory_index, 0);
+    assert_eq!(vm.register[1], 1);
+}
+
+#[test]
+fn index_is_not_masked_to_12_bits() {
+    // F000 FFF0: I = 0xFFF0, 6008: V0 = 8, F01E: I += V0.
+    let mut vm = xo_chip(&[0xF0, 0x00, 0xFF, 0xF0, 0x60, 0x08, 0xF0, 0x1E]);
+    run(&mut vm, 3);
+    assert_eq!(vm.memory_index, 0xFFF8);
+}
+
+#[test]
+fn register_ranges_are_saved_and_loaded() {
+    // 6101: V1 = 1, 6202: V2 = 2, 6303: V3 = 3, A300: I = 0x300, 5132: save V1..V3,
+    // 5312: load V3..V1 (reversed).
+    let mut vm = xo_chip(&[
+        0x61, 0x01, 0x62, 0x02, 0x63, 0x03, 0xA3, 0x00, 0x51, 0x32, 0x53, 0x13,
+    ]);
+    run(&mut vm, 5);
+    assert_eq!(&vm.memory[0x300..0x303], &[1, 2, 3]);
+    assert_eq!(vm.memory_index, 0x300);
+    run(&mut vm, 1);
+    assert_eq!(&vm.register[1..4], &[3, 2, 1]);
+}
+
+#[test]
+fn sprites_are_drawn_on_selected_planes() {
+    // F301: select both planes, A300: I = 0x300, D011: draw one row per plane.
+    let mut vm = xo_chip(&[0xF3, 0x01, 0xA3, 0x00, 0xD0, 0x11]);
+    vm.memory[0x300] = 0xC0;
+    vm.memory[0x301] = 0x60;
+    run(&mut vm, 3);
+    assert_eq!(vm.planes_at(0, 0), 0b01);
+    assert_eq!(vm.planes_at(1, 0), 0b11);
+    assert_eq!(vm.planes_at(2, 0), 0b10);
+    assert_eq!(vm.planes_at(3, 0), 0);
+}
+
+#[test]
+fn clear_screen_only_affects_selected_planes() {
+    // F301: select both planes, A300: I = 0x300, D011: draw, F201: select plane 2, 00E0: clear.
+    let mut vm = xo_chip(&[0xF3, 0x01, 0xA3, 0x00, 0xD0, 0x11, 0xF2, 0x01, 0x00, 0xE0]);
+    vm.memory[0x300] = 0x80;
+    vm.memory[0x301] = 0x80;
+    run(&mut vm, 5);
+    assert_eq!(vm.planes_at(0, 0), 0b01);
+}
+
+#[test]
+fn audio_pattern_and_pitch_are_loaded() {
+    // A300: I = 0x300, F002: load pattern, 6070: V0 = 0x70, F03A: pitch = V0.
+    let mut vm = xo_chip(&[0xA3, 0x00, 0xF0, 0x02, 0x60, 0x70, 0xF0, 0x3A]);
+    vm.memory[0x300..0x310].copy_from_slice(&[0xAA; 16]);
+    run(&mut vm, 4);
+    assert_eq!(vm.audio_pattern, [0xAA; 16]);
+    assert_eq!(vm.pitch, 0x70);
+}